@@ -4,7 +4,11 @@ pub mod span;
 mod strip;
 pub mod ty;
 
-use crate::{parse::Parser, token::Lexer, PipelineError, Source};
+use crate::{
+    parse::Parser,
+    token::{Lexer, Token},
+    PipelineError, Source,
+};
 use std::{fmt, rc::Rc};
 
 #[derive(Debug)]
@@ -39,7 +43,31 @@ impl TryFrom<&Source> for Ast {
 pub enum Decl {
     Function(Rc<node::FuncDecl>),
     Class(Rc<node::ClassDecl>),
+    Interface(Rc<node::InterfaceDecl>),
     Constant(Rc<node::ConstantDecl>),
+    Alias(Rc<node::AliasDecl>),
+}
+
+impl Decl {
+    pub fn name(&self) -> &Token {
+        match self {
+            Decl::Function(fun) => &fun.name,
+            Decl::Class(cls) => &cls.name,
+            Decl::Interface(interface) => &interface.name,
+            Decl::Constant(c) => &c.name,
+            Decl::Alias(alias) => &alias.name,
+        }
+    }
+
+    pub fn visibility(&self) -> node::Visibility {
+        match self {
+            Decl::Function(fun) => fun.visibility,
+            Decl::Class(cls) => cls.visibility,
+            Decl::Interface(interface) => interface.visibility,
+            Decl::Constant(c) => c.visibility,
+            Decl::Alias(alias) => alias.visibility,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -51,6 +79,7 @@ pub enum Stmt {
     If(Rc<node::If>),
     While(Rc<node::While>),
     For(Rc<node::For>),
+    Destructure(Rc<node::Destructure>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,11 +90,21 @@ pub enum Expr {
     Unary(Rc<node::Unary>),
     Ident(Rc<node::Ident>),
     Init(Rc<node::Init>),
+    Super(Rc<node::Super>),
+    Is(Rc<node::Is>),
+    Cast(Rc<node::Cast>),
+    Try(Rc<node::Try>),
+    Lambda(Rc<node::Lambda>),
+    Index(Rc<node::Index>),
+    Interpolation(Rc<node::Interpolation>),
     Range(Rc<node::Range>),
+    Tuple(Rc<node::Tuple>),
     Str(Rc<node::Literal<&'static str>>),
     Int(Rc<node::Literal<i64>>),
     Float(Rc<node::Literal<f64>>),
     Bool(Rc<node::Literal<bool>>),
+    Char(Rc<node::Literal<char>>),
+    Nil(Rc<node::Literal<()>>),
 }
 
 impl Expr {
@@ -84,14 +123,6 @@ impl Expr {
             panic!("called `Expr::init()` on a non-init")
         }
     }
-
-    pub fn range(&self) -> &node::Range {
-        if let Expr::Range(range) = self {
-            range
-        } else {
-            panic!("called `Expr::range()` on a non-range")
-        }
-    }
 }
 
 impl fmt::Display for Expr {
@@ -99,6 +130,7 @@ impl fmt::Display for Expr {
         match self {
             Expr::Call(call) => write!(f, "{}", call.left),
             Expr::Ident(ident) => write!(f, "{}", ident.name),
+            Expr::Super(sup) => write!(f, "super.{}", sup.method),
             _ => unimplemented!(),
         }
     }
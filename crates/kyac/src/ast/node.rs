@@ -3,9 +3,10 @@ use crate::{
         ty::{Type, TypeParameter},
         Decl, Expr, Stmt,
     },
-    token::Token,
+    token::{Kind, Token},
 };
 use std::{
+    fmt,
     rc::Rc,
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -18,6 +19,11 @@ pub struct FuncDecl {
     pub tp: Vec<TypeParameter>,
     pub body: Vec<Stmt>,
     pub external: bool,
+    /// Whether the function is a method declared without a body
+    pub r#abstract: bool,
+    /// Whether the function is a method called on its class rather than on an object
+    pub r#static: bool,
+    pub visibility: Visibility,
     pub id: usize,
 }
 
@@ -29,9 +35,8 @@ impl FuncDecl {
         tp: Vec<TypeParameter>,
         body: Vec<Stmt>,
         external: bool,
+        r#abstract: bool,
     ) -> Self {
-        static ID: AtomicUsize = AtomicUsize::new(0);
-        let id = ID.fetch_add(1, Ordering::SeqCst);
         Self {
             name,
             params,
@@ -39,10 +44,19 @@ impl FuncDecl {
             tp,
             body,
             external,
-            id,
+            r#abstract,
+            r#static: false,
+            visibility: Visibility::Public,
+            id: Self::next_id(),
         }
     }
 
+    /// A new ID, which also identifies functions that the compiler generates itself.
+    pub fn next_id() -> usize {
+        static ID: AtomicUsize = AtomicUsize::new(0);
+        ID.fetch_add(1, Ordering::SeqCst)
+    }
+
     pub fn wrapped(
         name: Token,
         params: Vec<Param>,
@@ -50,8 +64,12 @@ impl FuncDecl {
         tp: Vec<TypeParameter>,
         body: Vec<Stmt>,
         external: bool,
+        visibility: Visibility,
     ) -> Decl {
-        Decl::Function(Rc::new(Self::new(name, params, ty, tp, body, external)))
+        Decl::Function(Rc::new(Self {
+            visibility,
+            ..Self::new(name, params, ty, tp, body, external, false)
+        }))
     }
 }
 
@@ -61,23 +79,28 @@ pub struct ClassDecl {
     pub fields: Vec<Field>,
     pub methods: Vec<Rc<FuncDecl>>,
     pub parent: Option<Token>,
+    pub interfaces: Vec<Token>,
     pub tp: Option<Vec<TypeParameter>>,
+    pub r#abstract: bool,
+    /// Whether this is a struct, whose values hold their fields in place of a pointer to an
+    /// object. A struct has no parent, interfaces or type parameters.
+    pub r#struct: bool,
+    pub visibility: Visibility,
 }
 
-impl ClassDecl {
-    pub fn wrapped(
-        name: Token,
-        fields: Vec<Field>,
-        methods: Vec<Rc<FuncDecl>>,
-        parent: Option<Token>,
-        tp: Option<Vec<TypeParameter>>,
-    ) -> Decl {
-        Decl::Class(Rc::new(Self {
+#[derive(Debug, PartialEq)]
+pub struct InterfaceDecl {
+    pub name: Token,
+    pub methods: Vec<Rc<FuncDecl>>,
+    pub visibility: Visibility,
+}
+
+impl InterfaceDecl {
+    pub fn wrapped(name: Token, methods: Vec<Rc<FuncDecl>>, visibility: Visibility) -> Decl {
+        Decl::Interface(Rc::new(Self {
             name,
-            fields,
             methods,
-            parent,
-            tp,
+            visibility,
         }))
     }
 }
@@ -87,11 +110,38 @@ pub struct ConstantDecl {
     pub name: Token,
     pub ty: Type,
     pub expr: Expr,
+    pub visibility: Visibility,
 }
 
 impl ConstantDecl {
-    pub fn wrapped(name: Token, ty: Type, expr: Expr) -> Decl {
-        Decl::Constant(Rc::new(Self { name, ty, expr }))
+    pub fn wrapped(name: Token, ty: Type, expr: Expr, visibility: Visibility) -> Decl {
+        Decl::Constant(Rc::new(Self {
+            name,
+            ty,
+            expr,
+            visibility,
+        }))
+    }
+}
+
+/// A type alias such as `type Pair<T> = (T, T);`, which stands for its type wherever it is
+/// named, with its type parameters replaced by the type arguments it is given.
+#[derive(Debug)]
+pub struct AliasDecl {
+    pub name: Token,
+    pub tp: Vec<TypeParameter>,
+    pub ty: Type,
+    pub visibility: Visibility,
+}
+
+impl AliasDecl {
+    pub fn wrapped(name: Token, tp: Vec<TypeParameter>, ty: Type, visibility: Visibility) -> Decl {
+        Decl::Alias(Rc::new(Self {
+            name,
+            tp,
+            ty,
+            visibility,
+        }))
     }
 }
 
@@ -100,11 +150,37 @@ pub struct VarDecl {
     pub name: Token,
     pub ty: Type,
     pub expr: Expr,
+    /// Whether the variable is declared with `var` or `let mut`, and so can be assigned to
+    pub mutable: bool,
 }
 
 impl VarDecl {
-    pub fn wrapped(name: Token, ty: Type, expr: Expr) -> Stmt {
-        Stmt::Var(Rc::new(Self { name, ty, expr }))
+    pub fn wrapped(name: Token, ty: Type, expr: Expr, mutable: bool) -> Stmt {
+        Stmt::Var(Rc::new(Self {
+            name,
+            ty,
+            expr,
+            mutable,
+        }))
+    }
+}
+
+/// A declaration of a variable for each element of the tuple `expr`
+#[derive(Debug, PartialEq)]
+pub struct Destructure {
+    pub names: Vec<Token>,
+    pub expr: Expr,
+    /// Whether the variables are declared with `var` or `let mut`, and so can be assigned to
+    pub mutable: bool,
+}
+
+impl Destructure {
+    pub fn wrapped(names: Vec<Token>, expr: Expr, mutable: bool) -> Stmt {
+        Stmt::Destructure(Rc::new(Self {
+            names,
+            expr,
+            mutable,
+        }))
     }
 }
 
@@ -169,12 +245,8 @@ pub struct For {
 }
 
 impl For {
-    pub fn wrapped(index: Token, range: Expr, body: Vec<Stmt>) -> Stmt {
-        Stmt::For(Rc::new(Self {
-            index,
-            iter: range,
-            body,
-        }))
+    pub fn wrapped(index: Token, iter: Expr, body: Vec<Stmt>) -> Stmt {
+        Stmt::For(Rc::new(Self { index, iter, body }))
     }
 }
 
@@ -289,23 +361,159 @@ impl Init {
     }
 }
 
+/// A call of the parent class's implementation of `method` on `self`
+#[derive(Debug, PartialEq)]
+pub struct Super {
+    pub keyword: Token,
+    pub method: Token,
+    pub args: Vec<Expr>,
+    pub parens: (Token, Token),
+    pub delimiters: Vec<Token>,
+}
+
+impl Super {
+    pub fn wrapped(
+        keyword: Token,
+        method: Token,
+        args: Vec<Expr>,
+        parens: (Token, Token),
+        delimiters: Vec<Token>,
+    ) -> Expr {
+        Expr::Super(Rc::new(Self {
+            keyword,
+            method,
+            args,
+            parens,
+            delimiters,
+        }))
+    }
+}
+
+/// A test of whether `expr` is an instance of the class `ty` at runtime
+#[derive(Debug, PartialEq)]
+pub struct Is {
+    pub expr: Expr,
+    pub keyword: Token,
+    pub ty: Type,
+}
+
+impl Is {
+    pub fn wrapped(expr: Expr, keyword: Token, ty: Type) -> Expr {
+        Expr::Is(Rc::new(Self { expr, keyword, ty }))
+    }
+}
+
+/// An early return of the error held by the result `expr`, which otherwise evaluates to its value
+#[derive(Debug, PartialEq)]
+pub struct Try {
+    pub expr: Expr,
+    pub question: Token,
+}
+
+impl Try {
+    pub fn wrapped(expr: Expr, question: Token) -> Expr {
+        Expr::Try(Rc::new(Self { expr, question }))
+    }
+}
+
+/// A downcast of `expr` to the class `ty`, which is checked at runtime
+#[derive(Debug, PartialEq)]
+pub struct Cast {
+    pub expr: Expr,
+    pub keyword: Token,
+    pub ty: Type,
+}
+
+impl Cast {
+    pub fn wrapped(expr: Expr, keyword: Token, ty: Type) -> Expr {
+        Expr::Cast(Rc::new(Self { expr, keyword, ty }))
+    }
+}
+
+/// An anonymous function, which captures the local variables its body refers to
+#[derive(Debug, PartialEq)]
+pub struct Lambda {
+    pub keyword: Token,
+    /// The function itself, named by the `fun` keyword
+    pub function: Rc<FuncDecl>,
+}
+
+impl Lambda {
+    pub fn wrapped(keyword: Token, function: FuncDecl) -> Expr {
+        Expr::Lambda(Rc::new(Self {
+            keyword,
+            function: Rc::new(function),
+        }))
+    }
+}
+
+/// A string with expressions interpolated into it. The parts alternate between string literals
+/// and interpolated expressions, starting and ending with a literal.
+#[derive(Debug, PartialEq)]
+pub struct Interpolation {
+    pub parts: Vec<Expr>,
+}
+
+impl Interpolation {
+    pub fn wrapped(parts: Vec<Expr>) -> Expr {
+        Expr::Interpolation(Rc::new(Self { parts }))
+    }
+}
+
+/// The byte of the string `expr` at `subscript`
+#[derive(Debug, PartialEq)]
+pub struct Index {
+    pub expr: Expr,
+    pub subscript: Expr,
+    pub brackets: (Token, Token),
+}
+
+impl Index {
+    pub fn wrapped(expr: Expr, subscript: Expr, brackets: (Token, Token)) -> Expr {
+        Expr::Index(Rc::new(Self {
+            expr,
+            subscript,
+            brackets,
+        }))
+    }
+}
+
+/// A range of integers, such as `[0, 10]`, which includes its end, or `[0, 10)`, which doesn't.
+/// A step can follow the range, as in `[10, 0] by -2`.
 #[derive(Debug, PartialEq)]
 pub struct Range {
     pub start: Expr,
     pub end: Expr,
+    pub step: Option<Expr>,
+    pub inclusive: bool,
     pub brackets: (Token, Token),
 }
 
 impl Range {
-    pub fn wrapped(start: Expr, end: Expr, brackets: (Token, Token)) -> Expr {
+    pub fn wrapped(start: Expr, end: Expr, step: Option<Expr>, brackets: (Token, Token)) -> Expr {
         Expr::Range(Rc::new(Self {
             start,
             end,
+            step,
+            inclusive: brackets.1.kind == Kind::RightBracket,
             brackets,
         }))
     }
 }
 
+/// A tuple of two or more elements, such as `(1, "one")`
+#[derive(Debug, PartialEq)]
+pub struct Tuple {
+    pub elements: Vec<Expr>,
+    pub parens: (Token, Token),
+}
+
+impl Tuple {
+    pub fn wrapped(elements: Vec<Expr>, parens: (Token, Token)) -> Expr {
+        Expr::Tuple(Rc::new(Self { elements, parens }))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Literal<T> {
     pub value: T,
@@ -332,6 +540,14 @@ impl<T> Literal<T> {
     pub fn bool(value: bool, token: Token) -> Expr {
         Expr::Bool(Rc::new(Literal::new(value, token)))
     }
+
+    pub fn char(value: char, token: Token) -> Expr {
+        Expr::Char(Rc::new(Literal::new(value, token)))
+    }
+
+    pub fn nil(token: Token) -> Expr {
+        Expr::Nil(Rc::new(Literal::new((), token)))
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -358,14 +574,43 @@ impl Param {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
     pub name: Token,
     pub ty: Type,
+    /// The value the field takes when an initializer leaves it out
+    pub default: Option<Expr>,
+    pub visibility: Visibility,
 }
 
 impl Field {
-    pub fn new(name: Token, ty: Type) -> Self {
-        Self { name, ty }
+    pub fn new(name: Token, ty: Type, default: Option<Expr>, visibility: Visibility) -> Self {
+        Self {
+            name,
+            ty,
+            default,
+            visibility,
+        }
+    }
+}
+
+/// Where a field or method of a class, or a top-level declaration, can be used from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    /// Only within the methods of the class that declares it and its subclasses
+    Protected,
+    /// Only within the methods of the class that declares it, or for a top-level declaration,
+    /// within the module that declares it
+    Private,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "public"),
+            Visibility::Protected => write!(f, "protected"),
+            Visibility::Private => write!(f, "private"),
+        }
     }
 }
@@ -15,16 +15,26 @@ pub trait Combined {
 
 impl Combined for Type {
     fn start(&self) -> usize {
+        if self.is_optional() {
+            return self.params[0].start();
+        }
         self.base.span.column
     }
 
     fn end(&self) -> usize {
-        self.params
-            .last()
+        if self.is_optional() {
+            return self.base.span.column + self.base.span.length;
+        }
+        self.ret
+            .as_deref()
+            .or(self.params.last())
             .map_or(self.base.span.column + self.base.span.length, Combined::end)
     }
 
     fn line(&self) -> usize {
+        if self.is_optional() {
+            return self.params[0].line();
+        }
         self.base.span.line
     }
 }
@@ -39,6 +49,7 @@ impl Combined for Stmt {
             Stmt::If(cond) => cond.condition.start(),
             Stmt::While(l) => l.condition.start(),
             Stmt::For(l) => l.iter.start(),
+            Stmt::Destructure(d) => d.names.first().unwrap().span.column,
         }
     }
 
@@ -51,6 +62,7 @@ impl Combined for Stmt {
             Stmt::If(cond) => cond.condition.end(),
             Stmt::While(l) => l.condition.end(),
             Stmt::For(l) => l.iter.end(),
+            Stmt::Destructure(d) => d.expr.end(),
         }
     }
 
@@ -63,6 +75,7 @@ impl Combined for Stmt {
             Stmt::If(cond) => cond.condition.line(),
             Stmt::While(cond) => cond.condition.line(),
             Stmt::For(cond) => cond.iter.line(),
+            Stmt::Destructure(d) => d.names.first().unwrap().span.line,
         }
     }
 }
@@ -80,13 +93,26 @@ impl Combined for Expr {
             Expr::Int(i) => i.token.span.column,
             Expr::Float(f) => f.token.span.column,
             Expr::Bool(b) => b.token.span.column,
+            Expr::Char(c) => c.token.span.column,
+            Expr::Nil(n) => n.token.span.column,
             Expr::Init(init) => init.name.span.column,
+            Expr::Super(sup) => sup.keyword.span.column,
+            Expr::Is(is) => is.expr.start(),
+            Expr::Cast(cast) => cast.expr.start(),
+            Expr::Try(t) => t.expr.start(),
+            Expr::Lambda(lambda) => lambda.keyword.span.column,
+            Expr::Index(index) => index.expr.start(),
+            Expr::Interpolation(string) => string.parts.first().unwrap().start(),
+            Expr::Tuple(tuple) => tuple.parens.0.span.column,
         }
     }
 
     fn end(&self) -> usize {
         match self {
-            Expr::Range(range) => range.end.end(),
+            Expr::Range(range) => match &range.step {
+                Some(step) => step.end(),
+                None => range.brackets.1.span.column + 1,
+            },
             Expr::Access(access) => access.chain.last().unwrap().end(),
             Expr::Call(call) => call.parens.1.span.column + 1,
             Expr::Binary(binary) => binary.right.end(),
@@ -96,8 +122,19 @@ impl Combined for Expr {
             Expr::Int(i) => i.token.span.column + i.token.span.length,
             Expr::Float(f) => f.token.span.column + f.token.span.length,
             Expr::Bool(b) => b.token.span.column + b.token.span.length,
+            Expr::Char(c) => c.token.span.column + c.token.span.length,
+            Expr::Nil(n) => n.token.span.column + n.token.span.length,
             // TODO: support multi-line spans
             Expr::Init(init) => init.name.span.column + init.name.span.length,
+            Expr::Super(sup) => sup.parens.1.span.column + 1,
+            Expr::Is(is) => is.ty.end(),
+            Expr::Cast(cast) => cast.ty.end(),
+            Expr::Try(t) => t.question.span.column + 1,
+            // TODO: support multi-line spans
+            Expr::Lambda(lambda) => lambda.keyword.span.column + lambda.keyword.span.length,
+            Expr::Index(index) => index.brackets.1.span.column + 1,
+            Expr::Interpolation(string) => string.parts.last().unwrap().end(),
+            Expr::Tuple(tuple) => tuple.parens.1.span.column + 1,
         }
     }
 
@@ -113,7 +150,17 @@ impl Combined for Expr {
             Expr::Int(i) => i.token.span.line,
             Expr::Float(f) => f.token.span.line,
             Expr::Bool(b) => b.token.span.line,
+            Expr::Char(c) => c.token.span.line,
+            Expr::Nil(n) => n.token.span.line,
             Expr::Init(init) => init.name.span.line,
+            Expr::Super(sup) => sup.keyword.span.line,
+            Expr::Is(is) => is.expr.line(),
+            Expr::Cast(cast) => cast.expr.line(),
+            Expr::Try(t) => t.expr.line(),
+            Expr::Lambda(lambda) => lambda.keyword.span.line,
+            Expr::Index(index) => index.expr.line(),
+            Expr::Interpolation(string) => string.parts.first().unwrap().line(),
+            Expr::Tuple(tuple) => tuple.parens.0.span.line,
         }
     }
 }
@@ -1,21 +1,86 @@
-use crate::token::Token;
+use crate::token::{Kind, Token};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Type {
     pub base: Token,
+    /// The type arguments of a class, or the parameter types of a function type
     pub params: Vec<Type>,
+    /// The return type of a function type such as `fun(int): int`, if it is not void
+    pub ret: Option<Box<Type>>,
 }
 
 impl Type {
     pub fn new(base: Token, params: Vec<Type>) -> Self {
-        Self { base, params }
+        Self {
+            base,
+            params,
+            ret: None,
+        }
+    }
+
+    pub fn function(keyword: Token, params: Vec<Type>, ret: Option<Type>) -> Self {
+        Self {
+            base: keyword,
+            params,
+            ret: ret.map(Box::new),
+        }
+    }
+
+    /// A tuple type such as `(int, str)`, named by its opening parenthesis.
+    pub fn tuple(paren: Token, elements: Vec<Type>) -> Self {
+        Self {
+            base: paren,
+            params: elements,
+            ret: None,
+        }
+    }
+
+    /// An optional type such as `Node?`, named by its question mark.
+    pub fn optional(question: Token, inner: Type) -> Self {
+        Self {
+            base: question,
+            params: vec![inner],
+            ret: None,
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.base.kind == Kind::Fun
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        self.base.kind == Kind::LeftParen
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.base.kind == Kind::Question
     }
 }
 
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.base.lexeme.unwrap_or("no lexeme found"))
+        if self.is_function() {
+            let params: Vec<_> = self.params.iter().map(ToString::to_string).collect();
+            write!(f, "fun({})", params.join(", "))?;
+            return match &self.ret {
+                Some(ret) => write!(f, ": {ret}"),
+                None => Ok(()),
+            };
+        }
+        if self.is_tuple() {
+            let elements: Vec<_> = self.params.iter().map(ToString::to_string).collect();
+            return write!(f, "({})", elements.join(", "));
+        }
+        if self.is_optional() {
+            return write!(f, "{}?", self.params[0]);
+        }
+        write!(f, "{}", self.base.lexeme.unwrap_or("no lexeme found"))?;
+        if !self.params.is_empty() {
+            let params: Vec<_> = self.params.iter().map(ToString::to_string).collect();
+            write!(f, "<{}>", params.join(", "))?;
+        }
+        Ok(())
     }
 }
 
@@ -2,7 +2,7 @@ use crate::{
     backend::kyir::{
         alloc::Registers,
         arch::{armv8a::Armv8a, ArchInstr, FlowGraphMeta, Format},
-        ir::RelOp,
+        ir::{Conversion, FloatOp, Narrow, RelOp},
     },
     Frame,
 };
@@ -31,8 +31,21 @@ pub enum A64 {
     Sub(String, String, String),
     Mul(String, String, String),
     Div(String, String, String),
+    UnsignedDiv(String, String, String),
+    /// (dst, src, conversion)
+    Convert(String, String, Conversion),
+    /// (dst, dst, src, op, bits)
+    Float(String, String, String, FloatOp, u32),
+    /// (lhs, rhs, bits)
+    FloatCompare(String, String, u32),
+    /// (dst, src, offset, narrow)
+    LoadNarrow(String, String, i64, Narrow),
+    /// (src, addr, offset, narrow)
+    StoreNarrow(String, String, i64, Narrow),
     /// (dst, src)
     Move(String, String),
+    /// (dst, value) for a value that doesn't fit in a single `mov`
+    MoveWide(String, i64),
     /// (label, rel)
     Branch(String, Option<RelOp>),
     /// (label)
@@ -66,7 +79,11 @@ impl ArchInstr for A64 {
     }
 
     fn copy_int(dst: String, value: i64) -> Self {
-        A64::Move(dst, format!("#{value}"))
+        if (-0x10000..=0xFFFF).contains(&value) {
+            A64::Move(dst, format!("#{value}"))
+        } else {
+            A64::MoveWide(dst, value)
+        }
     }
 
     fn add(dst: String, src: String) -> Self {
@@ -85,10 +102,26 @@ impl ArchInstr for A64 {
         A64::Div(dst.clone(), dst, src)
     }
 
+    fn udiv(dst: String, src: String) -> Self {
+        A64::UnsignedDiv(dst.clone(), dst, src)
+    }
+
+    fn convert(dst: String, src: String, conversion: Conversion) -> Self {
+        A64::Convert(dst, src, conversion)
+    }
+
+    fn float(dst: String, src: String, op: FloatOp, bits: u32) -> Self {
+        A64::Float(dst.clone(), dst, src, op, bits)
+    }
+
     fn compare(lhs: String, rhs: String) -> Self {
         A64::Compare(lhs, rhs)
     }
 
+    fn float_compare(lhs: String, rhs: String, bits: u32) -> Self {
+        A64::FloatCompare(lhs, rhs, bits)
+    }
+
     fn load(dst: String, src: String, offset: i64) -> Self {
         A64::LoadImmediate(dst, src, offset)
     }
@@ -97,6 +130,14 @@ impl ArchInstr for A64 {
         A64::StoreImmediate(src, addr, offset)
     }
 
+    fn load_narrow(dst: String, src: String, offset: i64, narrow: Narrow) -> Self {
+        A64::LoadNarrow(dst, src, offset, narrow)
+    }
+
+    fn store_narrow(src: String, addr: String, offset: i64, narrow: Narrow) -> Self {
+        A64::StoreNarrow(src, addr, offset, narrow)
+    }
+
     fn branch(label: String) -> Self {
         A64::Branch(label, None)
     }
@@ -114,11 +155,14 @@ impl FlowGraphMeta for A64 {
     fn defines(&self) -> Vec<String> {
         match self {
             A64::LoadImmediate(dst, src, ..) if dst == src => vec![],
-            A64::LoadImmediate(dst, ..) | A64::LoadEffective(dst, ..) => {
-                vec![dst.clone()]
-            }
+            A64::LoadImmediate(dst, ..)
+            | A64::LoadEffective(dst, ..)
+            | A64::LabelAddress(dst, ..)
+            | A64::LoadNarrow(dst, ..)
+            | A64::Convert(dst, ..)
+            | A64::Move(dst, ..)
+            | A64::MoveWide(dst, ..) => vec![dst.clone()],
             A64::LoadPair(r1, r2) => vec![r1.clone(), r2.clone()],
-            A64::LabelAddress(dst, ..) | A64::Move(dst, ..) => vec![dst.clone()],
             _ => vec![],
         }
     }
@@ -131,15 +175,23 @@ impl FlowGraphMeta for A64 {
             A64::LoadImmediate(dst, src, ..) if src == dst => vec![src.clone()],
             A64::LoadImmediate(_, src, ..) if src == r.frame => vec![],
             A64::LoadImmediate(_, src, ..) => vec![src.clone()],
-            A64::LoadEffective(.., src) | A64::Move(_, src) => {
+            A64::LoadEffective(.., src)
+            | A64::Move(_, src)
+            | A64::Convert(_, src, _)
+            | A64::LoadNarrow(_, src, ..) => {
                 vec![src.clone()]
             }
             A64::StorePair(r1, r2)
+            | A64::StoreNarrow(r1, r2, ..)
+            | A64::UnsignedDiv(_, r1, r2)
             | A64::Add(_, r1, r2)
             | A64::Sub(_, r1, r2)
             | A64::Mul(_, r1, r2)
-            | A64::Div(_, r1, r2) => vec![r1.clone(), r2.clone()],
-            A64::Compare(lhs, rhs) => vec![lhs.clone(), rhs.clone()],
+            | A64::Div(_, r1, r2)
+            | A64::Float(_, r1, r2, ..) => vec![r1.clone(), r2.clone()],
+            A64::Compare(lhs, rhs) | A64::FloatCompare(lhs, rhs, _) => {
+                vec![lhs.clone(), rhs.clone()]
+            }
             A64::Call(ext) if ext.starts_with('T') => vec![ext.clone()],
             _ => vec![],
         }
@@ -187,8 +239,19 @@ impl Format for A64 {
             A64::Sub(dst, r1, r2) => A64::Sub(get(dst), get(r1), get(r2)),
             A64::Mul(dst, r1, r2) => A64::Mul(get(dst), get(r1), get(r2)),
             A64::Div(dst, r1, r2) => A64::Div(get(dst), get(r1), get(r2)),
+            A64::UnsignedDiv(dst, r1, r2) => A64::UnsignedDiv(get(dst), get(r1), get(r2)),
+            A64::Convert(dst, src, conversion) => A64::Convert(get(dst), get(src), conversion),
+            A64::Float(dst, r1, r2, op, bits) => A64::Float(get(dst), get(r1), get(r2), op, bits),
+            A64::LoadNarrow(dst, src, offset, narrow) => {
+                A64::LoadNarrow(get(dst), get(src), offset, narrow)
+            }
+            A64::StoreNarrow(src, dst, offset, narrow) => {
+                A64::StoreNarrow(get(src), get(dst), offset, narrow)
+            }
             A64::Move(dst, src) => A64::Move(get(dst), get(src)),
+            A64::MoveWide(dst, value) => A64::MoveWide(get(dst), value),
             A64::Compare(lhs, rhs) => A64::Compare(get(lhs), get(rhs)),
+            A64::FloatCompare(lhs, rhs, bits) => A64::FloatCompare(get(lhs), get(rhs), bits),
             A64::Call(ext) if ext.starts_with('T') => A64::Call(get(ext)),
             _ => self,
         }
@@ -216,7 +279,37 @@ impl fmt::Display for A64 {
             A64::Sub(dst, r1, r2) => write!(f, "{pad}sub {dst}, {r1}, {r2}"),
             A64::Mul(dst, r1, r2) => write!(f, "{pad}mul {dst}, {r1}, {r2}"),
             A64::Div(dst, r1, r2) => write!(f, "{pad}sdiv {dst}, {r1}, {r2}"),
+            A64::UnsignedDiv(dst, r1, r2) => write!(f, "{pad}udiv {dst}, {r1}, {r2}"),
+            A64::Convert(dst, src, conversion) => convert(f, dst, src, *conversion),
+            A64::Float(dst, r1, r2, op, bits) => float(f, dst, (r1, r2), *op, *bits),
+            A64::FloatCompare(lhs, rhs, bits) => {
+                float(f, "", (lhs, rhs), FloatOp::Cmp(RelOp::Equal), *bits)
+            }
+            A64::LoadNarrow(dst, src, offset, narrow) if narrow.signed => {
+                let size = ["b", "h", "w"][narrow.bytes.trailing_zeros() as usize];
+                write!(f, "{pad}ldrs{size} {dst}, [{src}, #{offset}]")
+            }
+            A64::LoadNarrow(dst, src, offset, narrow) => {
+                let size = ["b", "h", ""][narrow.bytes.trailing_zeros() as usize];
+                write!(f, "{pad}ldr{size} {}, [{src}, #{offset}]", word(dst))
+            }
+            A64::StoreNarrow(src, dst, offset, narrow) => {
+                let size = ["b", "h", ""][narrow.bytes.trailing_zeros() as usize];
+                write!(f, "{pad}str{size} {}, [{dst}, #{offset}]", word(src))
+            }
             A64::Move(dst, src) => write!(f, "{pad}mov {dst}, {src}"),
+            A64::MoveWide(dst, value) => {
+                // Each 16 bits are moved separately, skipping those (after the first) that are zero
+                let bits = u64::from_ne_bytes(value.to_ne_bytes());
+                write!(f, "{pad}movz {dst}, #{}", bits & 0xFFFF)?;
+                for shift in [16, 32, 48] {
+                    let chunk = (bits >> shift) & 0xFFFF;
+                    if chunk != 0 {
+                        write!(f, "\n{pad}movk {dst}, #{chunk}, lsl #{shift}")?;
+                    }
+                }
+                Ok(())
+            }
             A64::Branch(label, rel) => {
                 if let Some(rel) = rel {
                     write!(f, "{pad}b{rel} {label}")
@@ -232,3 +325,89 @@ impl fmt::Display for A64 {
         }
     }
 }
+
+/// The 32-bit view of the 64-bit register `reg`.
+fn word(reg: &str) -> String {
+    reg.strip_prefix('x')
+        .map_or_else(|| reg.to_owned(), |n| format!("w{n}"))
+}
+
+/// The view of the float register `n` for a float of `bits`
+fn fp(n: u8, bits: u32) -> String {
+    if bits == 32 {
+        format!("s{n}")
+    } else {
+        format!("d{n}")
+    }
+}
+
+/// The view of the general purpose register `reg` that holds a float of `bits`
+fn gp(reg: &str, bits: u32) -> String {
+    if bits == 32 {
+        word(reg)
+    } else {
+        reg.to_owned()
+    }
+}
+
+/// Floats are held as bits in general purpose registers, so the operands are moved through the
+/// scratch registers `v30` and `v31` to be operated on (or compared, which leaves `dst` alone).
+fn float(
+    f: &mut fmt::Formatter<'_>,
+    dst: &str,
+    (left, right): (&str, &str),
+    op: FloatOp,
+    bits: u32,
+) -> fmt::Result {
+    let pad = " ".repeat(8);
+    writeln!(f, "{pad}fmov {}, {}", fp(30, bits), gp(left, bits))?;
+    writeln!(f, "{pad}fmov {}, {}", fp(31, bits), gp(right, bits))?;
+    let op = match op {
+        FloatOp::Plus => "fadd",
+        FloatOp::Minus => "fsub",
+        FloatOp::Mul => "fmul",
+        FloatOp::Div => "fdiv",
+        FloatOp::Cmp(_) => return write!(f, "{pad}fcmp {}, {}", fp(30, bits), fp(31, bits)),
+    };
+    writeln!(
+        f,
+        "{pad}{op} {}, {}, {}",
+        fp(30, bits),
+        fp(30, bits),
+        fp(31, bits)
+    )?;
+    write!(f, "{pad}fmov {}, {}", gp(dst, bits), fp(30, bits))
+}
+
+/// Floats are held as bits in general purpose registers, so they're moved through the scratch
+/// register `v31` to be converted.
+fn convert(
+    f: &mut fmt::Formatter<'_>,
+    dst: &str,
+    src: &str,
+    conversion: Conversion,
+) -> fmt::Result {
+    let pad = " ".repeat(8);
+    let fp = |bits: u32| fp(31, bits);
+    match conversion {
+        Conversion::Extend { bits, signed } => {
+            let op = if signed { "sbfx" } else { "ubfx" };
+            write!(f, "{pad}{op} {dst}, {src}, #0, #{bits}")
+        }
+        Conversion::IntToFloat { signed, bits } => {
+            let op = if signed { "scvtf" } else { "ucvtf" };
+            writeln!(f, "{pad}{op} {}, {src}", fp(bits))?;
+            write!(f, "{pad}fmov {}, {}", gp(dst, bits), fp(bits))
+        }
+        Conversion::FloatToInt { signed, bits } => {
+            let op = if signed { "fcvtzs" } else { "fcvtzu" };
+            writeln!(f, "{pad}fmov {}, {}", fp(bits), gp(src, bits))?;
+            write!(f, "{pad}{op} {dst}, {}", fp(bits))
+        }
+        Conversion::Float { from, to } => {
+            writeln!(f, "{pad}fmov {}, {}", fp(from), gp(src, from))?;
+            writeln!(f, "{pad}fcvt {}, {}", fp(to), fp(from))?;
+            write!(f, "{pad}fmov {}, {}", gp(dst, to), fp(to))
+        }
+    }
+}
@@ -1,12 +1,12 @@
 pub mod isa;
 
 use crate::{
-    ast::node::FuncDecl,
     backend::kyir::{
         arch::{Location, RegisterMap},
         ir::{Expr, Mem, Temp},
         Frame,
     },
+    hir::node::FuncDecl,
 };
 use std::collections::HashMap;
 
@@ -21,30 +21,33 @@ pub struct Armv8a {
 impl Frame<isa::A64> for Armv8a {
     fn new(func: &FuncDecl) -> Self {
         let r = Self::registers();
-        assert!(func.params.len() <= 8);
+        // Each element of a tuple is passed in a register of its own
+        let params: Vec<_> = func
+            .params
+            .iter()
+            .flat_map(|param| {
+                param
+                    .ty
+                    .leaves()
+                    .into_iter()
+                    .map(|(path, ty)| (format!("{}{path}", param.name), ty))
+            })
+            .collect();
+        assert!(params.len() <= 8);
         let mut variables = HashMap::new();
         let mut offset = -i64::try_from(r.callee.len() * Self::word_size()).unwrap();
-        for (i, param) in func.params.iter().enumerate() {
+        for (i, (name, ty)) in params.iter().enumerate() {
             if i == 0 {
                 offset -= i64::try_from(Self::word_size()).unwrap();
             }
-            variables.insert(
-                param.name.to_string(),
-                Variable::new(
-                    offset,
-                    !matches!(param.ty.base.lexeme, Some("int" | "float" | "bool")),
-                ),
-            );
+            variables.insert(name.clone(), Variable::new(offset, ty.is_ptr()));
             offset -= i64::try_from(Self::word_size()).unwrap();
         }
         Self {
-            formals: func
-                .params
-                .iter()
-                .enumerate()
-                .map(|(i, _)| Formal::new(r.argument[i]))
+            formals: (0..params.len())
+                .map(|i| Formal::new(r.argument[i]))
                 .collect(),
-            label: func.name.to_string(),
+            label: func.label(),
             variables,
             offset,
         }
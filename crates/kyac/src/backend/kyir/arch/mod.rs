@@ -1,8 +1,12 @@
 pub mod armv8a;
 
 use crate::{
-    ast::node::FuncDecl,
-    backend::kyir::{alloc::Registers, AsmInstr, Expr, RelOp},
+    backend::kyir::{
+        alloc::Registers,
+        ir::{Conversion, FloatOp, Narrow},
+        AsmInstr, Expr, RelOp,
+    },
+    hir::node::FuncDecl,
 };
 use std::{collections::HashMap, fmt};
 
@@ -41,6 +45,10 @@ pub trait ArchInstr: FlowGraphMeta + Format + fmt::Debug + fmt::Display {
     fn label_address(dst: String, src: String) -> Self;
     fn load(dst: String, src: String, offset: i64) -> Self;
     fn store(src: String, addr: String, offset: i64) -> Self;
+    /// Loads a value of `narrow` bytes, extending it to the whole register
+    fn load_narrow(dst: String, src: String, offset: i64, narrow: Narrow) -> Self;
+    /// Stores the low `narrow` bytes of `src`
+    fn store_narrow(src: String, addr: String, offset: i64, narrow: Narrow) -> Self;
     /// `move` is a reserved keyword in Rust so we use an analogous term instead
     fn copy(dst: String, src: String) -> Self;
     fn copy_int(dst: String, value: i64) -> Self;
@@ -48,7 +56,12 @@ pub trait ArchInstr: FlowGraphMeta + Format + fmt::Debug + fmt::Display {
     fn sub(dst: String, src: String) -> Self;
     fn mul(dst: String, src: String) -> Self;
     fn div(dst: String, src: String) -> Self;
+    fn udiv(dst: String, src: String) -> Self;
+    fn convert(dst: String, src: String, conversion: Conversion) -> Self;
+    /// Applies `op` to the floats of `bits` in `dst` and `src`, leaving the result in `dst`
+    fn float(dst: String, src: String, op: FloatOp, bits: u32) -> Self;
     fn compare(lhs: String, rhs: String) -> Self;
+    fn float_compare(lhs: String, rhs: String, bits: u32) -> Self;
     fn branch(label: String) -> Self;
     fn cbranch(label: String, rel: RelOp) -> Self;
     fn call(ext: String) -> Self;
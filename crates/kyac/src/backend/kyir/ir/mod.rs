@@ -16,6 +16,7 @@ pub enum Expr {
     ConstLabel(String),
     Temp(Temp),
     Binary(Binary),
+    Convert(Convert),
     Mem(Mem),
     Call(Call),
     ESeq(ESeq),
@@ -93,10 +94,7 @@ impl Stmt {
 impl Expr {
     pub fn relation(&self) -> Option<RelOp> {
         match self {
-            Self::Binary(Binary {
-                op: BinOp::Cmp(rel),
-                ..
-            }) => Some(*rel),
+            Self::Binary(Binary { op, .. }) => op.relation(),
             Self::ConstInt(_) => Some(RelOp::Equal),
             _ => None,
         }
@@ -140,8 +138,54 @@ pub enum BinOp {
     Minus,
     Mul,
     Div,
+    UnsignedDiv,
     Xor,
     Cmp(RelOp),
+    /// An operation on two floats of `bits`
+    Float(FloatOp, u32),
+}
+
+impl BinOp {
+    /// The variant of this operation for unsigned operands.
+    pub fn unsigned(self) -> Self {
+        match self {
+            BinOp::Div => BinOp::UnsignedDiv,
+            BinOp::Cmp(rel) => BinOp::Cmp(rel.unsigned()),
+            op => op,
+        }
+    }
+
+    /// The variant of this operation for float operands of `bits`.
+    pub fn float(self, bits: u32) -> Self {
+        let op = match self {
+            BinOp::Plus => FloatOp::Plus,
+            BinOp::Minus => FloatOp::Minus,
+            BinOp::Mul => FloatOp::Mul,
+            BinOp::Div => FloatOp::Div,
+            BinOp::Cmp(rel) => FloatOp::Cmp(rel.float()),
+            op => return op,
+        };
+        BinOp::Float(op, bits)
+    }
+
+    /// The relation tested by a comparison, whether of integers or of floats
+    pub fn relation(self) -> Option<RelOp> {
+        match self {
+            BinOp::Cmp(rel) | BinOp::Float(FloatOp::Cmp(rel), _) => Some(rel),
+            _ => None,
+        }
+    }
+}
+
+/// An operation on floats. Floats are held in general purpose registers, so their operands are
+/// moved to the float registers to be operated on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FloatOp {
+    Plus,
+    Minus,
+    Mul,
+    Div,
+    Cmp(RelOp),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -152,6 +196,56 @@ pub enum RelOp {
     Greater,
     LessEqual,
     GreaterEqual,
+    /// The unsigned variants of `Less`, `Greater`, `LessEqual` and `GreaterEqual`
+    Below,
+    Above,
+    BelowEqual,
+    AboveEqual,
+}
+
+impl RelOp {
+    fn unsigned(self) -> Self {
+        match self {
+            RelOp::Less => RelOp::Below,
+            RelOp::Greater => RelOp::Above,
+            RelOp::LessEqual => RelOp::BelowEqual,
+            RelOp::GreaterEqual => RelOp::AboveEqual,
+            rel => rel,
+        }
+    }
+
+    /// The variant of this relation for comparing floats. A comparison involving NaN is unordered,
+    /// which the signed `Less` and `LessEqual` would take as true, so they test the flags like
+    /// their unsigned variants instead.
+    fn float(self) -> Self {
+        match self {
+            RelOp::Less => RelOp::Below,
+            RelOp::LessEqual => RelOp::BelowEqual,
+            rel => rel,
+        }
+    }
+}
+
+/// A change in the representation of a number held in a register. Floats are kept in general
+/// purpose registers, as the bits of a 64-bit float or in the low 32 bits for a 32-bit one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Conversion {
+    /// Sign or zero extends the low `bits` of an integer to the whole register
+    Extend { bits: u32, signed: bool },
+    /// Converts a signed or unsigned integer to a float of `bits`
+    IntToFloat { signed: bool, bits: u32 },
+    /// Truncates a float of `bits` towards zero, to a signed or unsigned integer
+    FloatToInt { signed: bool, bits: u32 },
+    /// Widens or narrows a float from `from` to `to` bits
+    Float { from: u32, to: u32 },
+}
+
+/// A value in memory narrower than a word: its size in bytes and whether it is sign extended when
+/// loaded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Narrow {
+    pub bytes: usize,
+    pub signed: bool,
 }
 
 impl From<Kind> for BinOp {
@@ -174,10 +268,9 @@ impl From<Kind> for BinOp {
 
 impl From<BinOp> for RelOp {
     fn from(value: BinOp) -> Self {
-        match value {
-            BinOp::Cmp(rel) => rel,
-            _ => panic!("Cannot convert {value:?} to RelOp"),
-        }
+        value
+            .relation()
+            .unwrap_or_else(|| panic!("Cannot convert {value:?} to RelOp"))
     }
 }
 
@@ -190,6 +283,10 @@ impl fmt::Display for RelOp {
             Self::Greater => "gt",
             Self::LessEqual => "le",
             Self::GreaterEqual => "ge",
+            Self::Below => "lo",
+            Self::Above => "hi",
+            Self::BelowEqual => "ls",
+            Self::AboveEqual => "hs",
         };
         write!(f, "{s}")
     }
@@ -1,4 +1,4 @@
-use crate::backend::kyir::ir::{BinOp, Expr, Stmt};
+use crate::backend::kyir::ir::{BinOp, Conversion, Expr, Narrow, Stmt};
 use std::{
     ops::Deref,
     sync::atomic::{AtomicUsize, Ordering},
@@ -148,10 +148,27 @@ impl Binary {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Convert {
+    pub conversion: Conversion,
+    pub expr: Box<Expr>,
+}
+
+impl Convert {
+    pub fn wrapped(conversion: Conversion, expr: Expr) -> Expr {
+        Expr::Convert(Self {
+            conversion,
+            expr: Box::new(expr),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Mem {
     pub base: Box<Expr>,
     pub offset: Const<i64>,
+    /// The size of the value in memory, if it is narrower than a word
+    pub narrow: Option<Narrow>,
 }
 
 impl Mem {
@@ -159,12 +176,21 @@ impl Mem {
         Self {
             base,
             offset: Const::new(offset),
+            narrow: None,
         }
     }
 
     pub fn wrapped(base: Expr, offset: i64) -> Expr {
         Expr::Mem(Self::new(Box::new(base), offset))
     }
+
+    /// A location holding a value of `narrow` bytes rather than a whole word.
+    pub fn narrow(base: Expr, offset: i64, narrow: Option<Narrow>) -> Expr {
+        Expr::Mem(Self {
+            narrow,
+            ..Self::new(Box::new(base), offset)
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
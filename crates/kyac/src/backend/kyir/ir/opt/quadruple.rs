@@ -1,9 +1,10 @@
-use crate::backend::kyir::ir::{Binary, CJump, Call, ESeq, Expr, Move, Seq, Stmt, Temp};
+use crate::backend::kyir::ir::{Binary, CJump, Call, Convert, ESeq, Expr, Move, Seq, Stmt, Temp};
 
 pub fn verify(stmt: &Stmt) {
     match stmt {
         Stmt::Move(m)
-            if matches!(*m.target, Expr::Temp(_)) && matches!(*m.expr, Expr::Binary(_)) => {} // #1
+            if matches!(*m.target, Expr::Temp(_))
+                && matches!(*m.expr, Expr::Binary(_) | Expr::Convert(_)) => {} // #1
         Stmt::Move(m)
             if matches!(*m.target, Expr::Temp(_))
                 && matches!(
@@ -78,7 +79,7 @@ impl Quadruple for Expr {
             ),
             Self::Binary(bin) => {
                 let wrap = |side: Box<Expr>| {
-                    if matches!(*side, Expr::Binary(_)) {
+                    if matches!(*side, Expr::Binary(_) | Expr::Convert(_)) {
                         let tmp = Temp::next();
                         ESeq::wrapped(
                             Move::wrapped(Temp::wrapped(tmp.clone()), side.quadruple()),
@@ -95,6 +96,26 @@ impl Quadruple for Expr {
                     Temp::wrapped(tmp),
                 )
             }
+            Self::Convert(convert) => {
+                let expr = convert.expr.quadruple();
+                let expr = if matches!(expr, Expr::ESeq(_) | Expr::Temp(_)) {
+                    expr
+                } else {
+                    let tmp = Temp::next();
+                    ESeq::wrapped(
+                        Move::wrapped(Temp::wrapped(tmp.clone()), expr),
+                        Temp::wrapped(tmp),
+                    )
+                };
+                let tmp = Temp::next();
+                ESeq::wrapped(
+                    Move::wrapped(
+                        Temp::wrapped(tmp.clone()),
+                        Convert::wrapped(convert.conversion, expr),
+                    ),
+                    Temp::wrapped(tmp),
+                )
+            }
             _ => self,
         }
     }
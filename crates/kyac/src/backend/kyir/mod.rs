@@ -4,32 +4,28 @@ mod ir;
 mod translate;
 
 use crate::{
-    ast::Decl,
     backend::kyir::{
         alloc::Registers,
         arch::{ArchInstr, FlowGraphMeta, Frame},
         ir::{
-            BinOp, Binary, CJump, Call, Const, Expr, Jump, Label, Mem, Move, RelOp, Seq, Stmt, Temp,
+            BinOp, Binary, CJump, Call, Const, Convert, Expr, FloatOp, Jump, Label, Mem, Move,
+            RelOp, Seq, Stmt, Temp,
         },
         translate::Translator,
     },
-    pass::{ResolvedMetaInfo, SymbolTable},
+    hir::Decl,
+    pass::SymbolTable,
 };
 use std::{
     collections::HashMap,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-pub fn asm<I: ArchInstr, F: Frame<I>>(
-    ast: &[Decl],
-    symbols: &SymbolTable,
-    meta: &ResolvedMetaInfo,
-    opt: bool,
-) -> String {
-    let mut translator: Translator<I, F> = Translator::new(symbols, meta);
-    let naive = translator.translate(ast);
+pub fn asm<I: ArchInstr, F: Frame<I>>(hir: &[Decl], symbols: &SymbolTable, opt: bool) -> String {
+    let mut translator: Translator<I, F> = Translator::new(symbols);
+    let naive = translator.translate(hir);
     let mut codegen: Codegen<I, F> =
-        Codegen::new(translator.functions(), translator.constants(), ast);
+        Codegen::new(translator.functions(), translator.constants(), hir);
     let instrs = if opt {
         let quadruples: Vec<_> = translate::canonicalize(ir::opt::optimize(naive))
             .into_iter()
@@ -65,22 +61,16 @@ impl<'a, I: ArchInstr, F: Frame<I>> Codegen<'a, I, F> {
     fn new(
         functions: &'a HashMap<usize, F>,
         constants: &'a HashMap<String, Vec<String>>,
-        ast: &[Decl],
+        hir: &[Decl],
     ) -> Self {
         Self {
-            idents: ast
+            idents: hir
                 .iter()
                 .filter_map(|decl| {
                     if let Decl::Function(decl) = decl {
-                        Some(vec![(decl.name.to_string(), decl.id)])
+                        (!decl.external).then(|| vec![(decl.label(), decl.id)])
                     } else if let Decl::Class(class) = decl {
-                        Some(
-                            class
-                                .methods
-                                .iter()
-                                .map(|m| (format!("{}.{}", class.name, m.name), m.id))
-                                .collect(),
-                        )
+                        Some(class.methods.iter().map(|m| (m.label(), m.id)).collect())
                     } else {
                         None
                     }
@@ -110,7 +100,7 @@ impl<'a, I: ArchInstr, F: Frame<I>> Codegen<'a, I, F> {
                     I::proc(addr.clone()),
                     I::data_fragment(
                         String::from("asciz"),
-                        s.iter().map(|s| format!("\"{s}\"")).collect(),
+                        s.iter().map(|s| format!("\"{}\"", escape(s))).collect(),
                     ),
                 ]
             })
@@ -163,6 +153,7 @@ impl Assembly<String> for Expr {
     fn assembly<I: ArchInstr, F: Frame<I>>(&self, codegen: &mut Codegen<I, F>) -> String {
         match self {
             Self::Binary(bin) => bin.assembly(codegen),
+            Self::Convert(convert) => convert.assembly(codegen),
             Self::ConstInt(i) => i.assembly(codegen),
             Self::Mem(mem) => mem.assembly(codegen),
             Self::Call(call) => call.assembly(codegen),
@@ -177,7 +168,7 @@ impl Assembly<String> for Expr {
                 codegen.emit(I::label_address(tmp.clone(), label.clone()));
                 tmp
             }
-            Self::ConstFloat(_) => todo!(),
+            Self::ConstFloat(f) => f.assembly(codegen),
             Self::ESeq(eseq) => panic!(
                 "`Expr::ESeq` not removed by canonicalization (id: {})",
                 eseq.id
@@ -189,7 +180,17 @@ impl Assembly<String> for Expr {
 impl Assembly<String> for Const<i64> {
     fn assembly<I: ArchInstr, F: Frame<I>>(&self, codegen: &mut Codegen<I, F>) -> String {
         let name = Temp::next();
-        codegen.emit(I::copy(name.clone(), format!("#{}", self.value)));
+        codegen.emit(I::copy_int(name.clone(), self.value));
+        name
+    }
+}
+
+impl Assembly<String> for Const<f64> {
+    fn assembly<I: ArchInstr, F: Frame<I>>(&self, codegen: &mut Codegen<I, F>) -> String {
+        // Floats are held as their bits in general purpose registers
+        let name = Temp::next();
+        let bits = i64::from_ne_bytes(self.value.to_bits().to_ne_bytes());
+        codegen.emit(I::copy_int(name.clone(), bits));
         name
     }
 }
@@ -203,7 +204,12 @@ impl Assembly<String> for Binary {
             BinOp::Minus => I::sub(left.clone(), right.clone()),
             BinOp::Mul => I::mul(left.clone(), right.clone()),
             BinOp::Div => I::div(left.clone(), right.clone()),
+            BinOp::UnsignedDiv => I::udiv(left.clone(), right.clone()),
             BinOp::Cmp(_) => I::compare(left.clone(), right.clone()),
+            BinOp::Float(FloatOp::Cmp(_), bits) => {
+                I::float_compare(left.clone(), right.clone(), bits)
+            }
+            BinOp::Float(op, bits) => I::float(left.clone(), right.clone(), op, bits),
             BinOp::Xor => todo!(),
         };
         codegen.emit(instr);
@@ -211,14 +217,37 @@ impl Assembly<String> for Binary {
     }
 }
 
+impl Assembly<String> for Convert {
+    fn assembly<I: ArchInstr, F: Frame<I>>(&self, codegen: &mut Codegen<I, F>) -> String {
+        let src = self.expr.assembly(codegen);
+        let dst = Temp::next();
+        codegen.emit(I::convert(dst.clone(), src, self.conversion));
+        dst
+    }
+}
+
+impl Mem {
+    fn load<I: ArchInstr>(&self, dst: String) -> I {
+        let (base, offset) = (self.base.temp().unwrap(), self.offset.value.abs());
+        match self.narrow {
+            Some(narrow) => I::load_narrow(dst, base, offset, narrow),
+            None => I::load(dst, base, offset),
+        }
+    }
+
+    fn store<I: ArchInstr>(&self, src: String) -> I {
+        let (base, offset) = (self.base.temp().unwrap(), self.offset.value.abs());
+        match self.narrow {
+            Some(narrow) => I::store_narrow(src, base, offset, narrow),
+            None => I::store(src, base, offset),
+        }
+    }
+}
+
 impl Assembly<String> for Mem {
     fn assembly<I: ArchInstr, F: Frame<I>>(&self, codegen: &mut Codegen<I, F>) -> String {
         let dst = Temp::next();
-        codegen.emit(I::load(
-            dst.clone(),
-            self.base.temp().unwrap(),
-            self.offset.value.abs(),
-        ));
+        codegen.emit(self.load(dst.clone()));
         dst
     }
 }
@@ -268,16 +297,14 @@ impl Assembly<()> for Move {
         let store = matches!(*self.target, Expr::Mem(_) | Expr::Temp(_));
         if store {
             let instr = if let Expr::Mem(mem) = &*self.target {
-                let src = self.expr.assembly(codegen);
-                I::store(src, mem.base.temp().unwrap(), mem.offset.value.abs())
+                mem.store(self.expr.assembly(codegen))
             } else {
                 I::copy(self.target.assembly(codegen), self.expr.assembly(codegen))
             };
             codegen.emit(instr);
         } else {
             let instr = if let Expr::Mem(mem) = &*self.expr {
-                let dst = self.target.assembly(codegen);
-                I::load(dst, mem.base.temp().unwrap(), mem.offset.value.abs())
+                mem.load(self.target.assembly(codegen))
             } else if let Expr::ConstInt(ref i) = *self.expr {
                 I::copy_int(self.target.assembly(codegen), i.value)
             } else {
@@ -323,16 +350,45 @@ impl<I: ArchInstr> AsmInstr<I> {
     }
 }
 
+/// Escapes `s` for use in a quoted assembler string. Bytes outside of printable ASCII are written
+/// as octal escapes.
+fn escape(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'"' => String::from("\\\""),
+            b'\\' => String::from("\\\\"),
+            b'\n' => String::from("\\n"),
+            b'\t' => String::from("\\t"),
+            b' '..=b'~' => char::from(b).to_string(),
+            b => format!("\\{b:03o}"),
+        })
+        .collect()
+}
+
 const BUILTINS: &[&str] = &[
     "max_int",
     "min_int",
     "max_float",
     "min_float",
     "println_bool",
+    "println_char",
     "println_int",
     "println_float",
     "println_str",
+    "len",
+    "substring",
+    "int_to_str",
+    "float_to_str",
+    "bool_to_str",
+    "char_to_str",
     // internal
     "alloc",
     "init_array",
+    "instance_of",
+    "checked_cast",
+    "str_concat",
+    "str_compare",
+    "str_index",
+    "str_char",
+    "str_next",
 ];
@@ -44,10 +44,14 @@ impl BasicBlocks {
             match stmt {
                 Stmt::Label(l) => {
                     Self::consume(function);
-                    if let Some(Stmt::Label(ll)) = Self::peek(function) {
-                        substitutions.push((ll.name, l.clone().name));
-                    }
-                    if !body.is_empty() {
+                    if body.is_empty() && label != func.label() {
+                        // A label at the very start of a block is just another name for that block
+                        if l.name != label {
+                            substitutions.push((l.name, label.clone()));
+                        }
+                    } else {
+                        // The function label starts the prologue, so a label directly after it (such
+                        // as a loop test) still needs a block of its own to jump back to
                         body.push(Jump::wrapped(l.clone().name));
                         let mut rest = Self::block(l.name, function, substitutions, func);
                         blocks.append(&mut rest);
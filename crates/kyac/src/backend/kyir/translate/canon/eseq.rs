@@ -20,6 +20,9 @@ impl<'a> ESeqs<'a> for Expr {
             Expr::Mem(mem) => {
                 mem.base.replace(search, temp);
             }
+            Expr::Convert(convert) => {
+                convert.expr.replace(search, temp);
+            }
             Expr::Call(call) => {
                 for arg in &mut call.args {
                     arg.replace(search, temp);
@@ -47,6 +50,9 @@ impl<'a> ESeqs<'a> for Expr {
             Expr::Mem(mem) => {
                 mem.base.eseqs(list);
             }
+            Expr::Convert(convert) => {
+                convert.expr.eseqs(list);
+            }
             Expr::Call(call) => {
                 for arg in &call.args {
                     arg.eseqs(list);
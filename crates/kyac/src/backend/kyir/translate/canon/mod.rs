@@ -75,14 +75,20 @@ fn update(expr: &Expr, ir: &mut Vec<Stmt>, replacements: &mut Vec<(usize, Box<Ex
         let Expr::ESeq(eseq) = expr else {
             panic!("Expected `Expr::ESeq`")
         };
-        if let Stmt::Seq(seq) = *eseq.stmt.clone() {
-            ir.push(*seq.left);
-            if let Some(right) = seq.right {
-                ir.push(*right);
-            }
-        } else {
-            ir.push(*eseq.stmt.clone());
-        }
+        flatten(*eseq.stmt.clone(), ir);
         replacements.push((eseq.id, eseq.expr.clone()));
     }
 }
+
+/// Pushes the statements of a (possibly nested) sequence in order, so that any jumps and labels
+/// in it are seen when building basic blocks.
+fn flatten(stmt: Stmt, ir: &mut Vec<Stmt>) {
+    if let Stmt::Seq(seq) = stmt {
+        flatten(*seq.left, ir);
+        if let Some(right) = seq.right {
+            flatten(*right, ir);
+        }
+    } else {
+        ir.push(stmt);
+    }
+}
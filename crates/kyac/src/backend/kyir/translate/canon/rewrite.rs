@@ -1,6 +1,6 @@
 use crate::backend::kyir::{
     ir::Move,
-    translate::{Binary, Call, ESeq, Seq},
+    translate::{Binary, Call, Convert, ESeq, Seq},
     Expr, Stmt, Temp,
 };
 
@@ -65,6 +65,9 @@ impl Rewrite<Expr> for Expr {
                 let right = bin.right.rewrite(false);
                 Binary::wrapped(bin.op, left, right)
             }
+            Expr::Convert(convert) => {
+                Convert::wrapped(convert.conversion, convert.expr.rewrite(false))
+            }
             _ => self,
         }
     }
@@ -4,21 +4,31 @@ pub(super) use canon::canonicalize;
 
 #[allow(clippy::wildcard_imports)]
 use crate::{
-    ast::{self, node::FuncDecl, ty::Type, Decl as AstDecl, Expr as AstExpr, Stmt as AstStmt},
+    ast,
     backend::kyir::{
         arch::{ArchInstr, Frame},
         ir::*,
     },
-    pass::{ResolvedMetaInfo, Symbol, SymbolTable},
-    token::{Kind, Span, Token},
+    builtins,
+    hir::{
+        self,
+        node::{Callee, FuncDecl},
+        Decl as HirDecl, Expr as HirExpr, Stmt as HirStmt, Type,
+    },
+    pass::{Symbol, SymbolTable},
+    token::Kind,
 };
-use std::{collections::HashMap, ops::Sub};
+use std::{collections::HashMap, ops::Sub, rc::Rc};
 
 pub struct Translator<'a, I: ArchInstr, F: Frame<I>> {
     functions: HashMap<usize, F>,
     function: Option<usize>,
-    meta: &'a ResolvedMetaInfo,
     symbols: &'a SymbolTable,
+    classes: HashMap<String, Rc<hir::node::ClassDecl>>,
+    /// The interface methods, in the order of their slots at the start of each method array
+    interfaces: Vec<(String, String)>,
+    /// The range of class IDs covering each class and its subclasses
+    class_ids: HashMap<String, (i64, i64)>,
     ctx: Context,
     _isa: std::marker::PhantomData<I>,
 }
@@ -27,12 +37,10 @@ struct Context {
     ret: bool,
     name: Vec<String>,
     constants: Constants,
-    mem: Option<Mem>,
-    stmts: Vec<Stmt>,
 }
 
 impl<'a, I: ArchInstr, F: Frame<I>> Translator<'a, I, F> {
-    pub fn new(symbols: &'a SymbolTable, meta: &'a ResolvedMetaInfo) -> Self {
+    pub fn new(symbols: &'a SymbolTable) -> Self {
         Self {
             _isa: std::marker::PhantomData,
             functions: HashMap::new(),
@@ -41,17 +49,216 @@ impl<'a, I: ArchInstr, F: Frame<I>> Translator<'a, I, F> {
                 ret: false,
                 constants: Constants::new(),
                 name: vec![],
-                mem: None,
-                stmts: vec![],
             },
             symbols,
-            meta,
+            classes: HashMap::new(),
+            interfaces: symbols.interface_methods(),
+            class_ids: symbols.class_ids(),
         }
     }
 
     #[must_use]
-    pub fn translate(&mut self, ast: &[AstDecl]) -> Vec<Stmt> {
-        ast.iter().flat_map(|decl| decl.translate(self)).collect()
+    pub fn translate(&mut self, hir: &[HirDecl]) -> Vec<Stmt> {
+        for decl in hir {
+            if let HirDecl::Class(cls) = decl {
+                self.classes.insert(cls.name.clone(), Rc::clone(cls));
+            }
+        }
+        hir.iter().flat_map(|decl| decl.translate(self)).collect()
+    }
+
+    /// The labels held by the method array of `class`. The array starts with a slot for every
+    /// interface method in the program, which is filled in if the class implements the interface,
+    /// followed by the class's own dispatch table.
+    fn method_array(&self, class: &Rc<ast::node::ClassDecl>) -> Vec<Option<String>> {
+        let methods = Symbol::Class(Rc::clone(class)).methods(self.symbols);
+        let interfaces: Vec<_> = Symbol::interfaces(class, self.symbols)
+            .iter()
+            .map(|interface| interface.name.to_string())
+            .collect();
+        self.interfaces
+            .iter()
+            .map(|(interface, name)| {
+                interfaces
+                    .contains(interface)
+                    .then(|| {
+                        methods
+                            .iter()
+                            .find(|(_, m)| m.name == **name)
+                            .map(|(label, _)| label.clone())
+                    })
+                    .flatten()
+            })
+            .chain(methods.iter().map(|(label, _)| Some(label.clone())))
+            .collect()
+    }
+
+    /// Calls the runtime function `name`, returning a temporary that holds its result.
+    fn runtime_call(&mut self, name: &str, args: Vec<Expr>, ptr: bool) -> Expr {
+        let r = F::registers();
+        let temp = Temp::next();
+        let id = self.function.unwrap();
+        let frame = self.functions.get_mut(&id).unwrap();
+        let saved = frame.allocate(&temp, ptr);
+        let stmts = [
+            Stmt::Expr(Box::new(Call::wrapped(name.into(), args))),
+            Move::wrapped(saved.clone(), Temp::wrapped(r.ret.into())),
+        ];
+        ESeq::wrapped(Stmt::from(&stmts[..]), saved)
+    }
+
+    /// The offset of field `index` from the start of an instance of `class`, along with its size
+    /// when it is narrower than a word. A struct field is embedded in the instance, so it has an
+    /// offset for each of its leaves.
+    fn field(&self, class: &str, index: usize) -> Vec<(i64, Option<Narrow>)> {
+        let class = &self.classes[class];
+        let offsets = runtime::field_offsets(&class.descriptor(&HashMap::new()));
+        let start: usize = class.layout[..index]
+            .iter()
+            .map(|ty| ty.leaves().len())
+            .sum();
+        let leaves = class.layout[index].leaves().into_iter();
+        leaves
+            .zip(&offsets[start..])
+            .map(|((_, ty), &offset)| {
+                let narrow = match ty {
+                    Type::Integer(bits, signed) if bits < 64 => Some(Narrow {
+                        bytes: (bits / 8) as usize,
+                        signed,
+                    }),
+                    Type::F32 | Type::Char => Some(Narrow {
+                        bytes: 4,
+                        signed: false,
+                    }),
+                    _ => None,
+                };
+                (offset.try_into().unwrap(), narrow)
+            })
+            .collect()
+    }
+
+    /// Translates an expression of tuple type, returning the statements that evaluate it along
+    /// with the frame slot holding each of its elements, in the order given by `Type::leaves`.
+    /// Reading an element of a variable reads its slot directly, while any other tuple is stored
+    /// in slots of its own. Results are held as tuples too.
+    fn tuple(&mut self, expr: &HirExpr) -> (Vec<Stmt>, Vec<Expr>) {
+        match expr {
+            HirExpr::Ident(ident) => {
+                let leaves = ident.ty.leaves().into_iter();
+                let frame = self.frame();
+                let leaves = leaves.map(|(path, _)| frame.get(&format!("{}{path}", ident.name)));
+                (vec![], leaves.collect())
+            }
+            HirExpr::Tuple(tuple) => {
+                let (mut stmts, mut leaves) = (vec![], vec![]);
+                for element in &tuple.elements {
+                    let (mut element, mut slots) = self.element(element);
+                    stmts.append(&mut element);
+                    leaves.append(&mut slots);
+                }
+                (stmts, leaves)
+            }
+            HirExpr::Wrap(wrap) => {
+                let elements = wrap.ty.elements().unwrap();
+                let (stmts, mut leaves) = self.element(&wrap.value);
+                // The half of the result that is not held is zeroed
+                let zeroed = if wrap.ok { &elements[2] } else { &elements[1] };
+                let zeroed = std::iter::repeat(Const::<i64>::int(0)).take(zeroed.leaves().len());
+                if wrap.ok {
+                    leaves.extend(zeroed);
+                } else {
+                    leaves.splice(0..0, zeroed);
+                }
+                leaves.insert(0, Const::<i64>::int(i64::from(wrap.ok)));
+                (stmts, leaves)
+            }
+            HirExpr::Try(t) => self.propagate(t),
+            HirExpr::Element(element) => {
+                let (stmts, leaves) = self.tuple(&element.tuple);
+                (stmts, leaves[element.leaves()].to_vec())
+            }
+            HirExpr::Call(call) => call.invoke(self),
+            HirExpr::Access(access) => {
+                // The struct is copied out of the object, so that it is not changed by later
+                // assignments to the object. Each leaf is loaded into a temporary first, since
+                // these statements end up nested in an expression.
+                let (load, places) = access.places(self);
+                let mut stmts = vec![load];
+                let leaves = access.ty.leaves().into_iter().zip(places);
+                let id = self.function.unwrap();
+                let frame = self.functions.get_mut(&id).unwrap();
+                let slots = leaves
+                    .map(|((_, ty), place)| {
+                        let (temp, slot) =
+                            (Temp::next(), frame.allocate(&Temp::next(), ty.is_ptr()));
+                        stmts.push(Stmt::checked_move(Temp::wrapped(temp.clone()), place));
+                        stmts.push(Stmt::checked_move(slot.clone(), Temp::wrapped(temp)));
+                        slot
+                    })
+                    .collect();
+                (stmts, slots)
+            }
+            _ => unreachable!("not an expression of tuple type"),
+        }
+    }
+
+    /// Translates the target of an assignment, returning the statements that evaluate it along
+    /// with the location of each of its leaves. A struct embedded in an object is assigned to in
+    /// place, while anything else is held in slots of the frame.
+    fn places(&mut self, target: &HirExpr) -> (Vec<Stmt>, Vec<Expr>) {
+        match target {
+            HirExpr::Access(access) => {
+                let (load, places) = access.places(self);
+                (vec![load], places)
+            }
+            HirExpr::Element(element) => {
+                let (stmts, places) = self.places(&element.tuple);
+                (stmts, places[element.leaves()].to_vec())
+            }
+            target => self.tuple(target),
+        }
+    }
+
+    /// Translates an element of a tuple or result, storing it in a slot of its own unless it is
+    /// a tuple itself.
+    fn element(&mut self, element: &HirExpr) -> (Vec<Stmt>, Vec<Expr>) {
+        let ty = element.ty();
+        if ty.elements().is_some() {
+            return self.tuple(element);
+        }
+        let value = element.translate(self);
+        let id = self.function.unwrap();
+        let frame = self.functions.get_mut(&id).unwrap();
+        let slot = frame.allocate(&Temp::next(), ty.is_ptr());
+        (vec![Stmt::checked_move(slot.clone(), value)], vec![slot])
+    }
+
+    /// Translates a `?` on a result, which returns a result holding its error from the function
+    /// if it has one. Otherwise, it evaluates to the leaves of the result's value.
+    fn propagate(&mut self, t: &hir::node::Try) -> (Vec<Stmt>, Vec<Expr>) {
+        let r = F::registers();
+        let (mut stmts, leaves) = self.tuple(&t.expr);
+        let values = t.ty.leaves().len();
+        let mut leaves = leaves.into_iter();
+        let ok = leaves.next().unwrap();
+        let value: Vec<_> = leaves.by_ref().take(values).collect();
+        // The function's result holds no value, which is zeroed
+        let ret = t.ret.elements().unwrap();
+        let zeroed = std::iter::repeat(Const::<i64>::int(0)).take(1 + ret[1].leaves().len());
+        let (fail, succeed) = (Label::next(), Label::next());
+        stmts.push(CJump::wrapped(
+            BinOp::Cmp(RelOp::Equal),
+            Binary::wrapped(BinOp::Cmp(RelOp::Equal), ok, Const::<i64>::int(0)),
+            fail.clone(),
+            succeed.clone(),
+        ));
+        stmts.push(Label::wrapped(fail));
+        for (leaf, register) in zeroed.chain(leaves).zip(r.argument) {
+            stmts.push(Stmt::checked_move(Temp::wrapped((*register).into()), leaf));
+        }
+        stmts.push(Jump::wrapped(format!("{}.epilogue", self.frame().label())));
+        stmts.push(Label::wrapped(succeed));
+        (stmts, value)
     }
 
     fn frame(&self) -> &F {
@@ -72,184 +279,571 @@ trait Translate<R> {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> R;
 }
 
-impl Translate<Expr> for AstExpr {
+impl Translate<Expr> for HirExpr {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
         match self {
-            AstExpr::Int(i) => i.translate(translator),
-            AstExpr::Float(f) => f.translate(translator),
-            AstExpr::Bool(b) => b.translate(translator),
-            AstExpr::Range(_) => unimplemented!("ranges are not valid as standalone expressions"),
-            AstExpr::Str(s) => s.translate(translator),
-            AstExpr::Binary(binary) => binary.translate(translator),
-            AstExpr::Call(call) => call.translate(translator),
-            AstExpr::Ident(ident) => ident.translate(translator),
-            AstExpr::Unary(unary) => unary.translate(translator),
-            AstExpr::Access(access) => access.translate(translator),
-            AstExpr::Init(init) => init.translate(translator),
+            HirExpr::Int(i) => i.translate(translator),
+            HirExpr::Float(f) => f.translate(translator),
+            HirExpr::Bool(b) => b.translate(translator),
+            HirExpr::Char(c) => c.translate(translator),
+            // `nil` is a null pointer
+            HirExpr::Nil(_) => Const::<i64>::int(0),
+            HirExpr::Str(s) => s.translate(translator),
+            HirExpr::Binary(binary) => binary.translate(translator),
+            HirExpr::Call(call) => call.translate(translator),
+            HirExpr::Ident(ident) => ident.translate(translator),
+            HirExpr::Unary(unary) => unary.translate(translator),
+            HirExpr::Access(access) => access.translate(translator),
+            HirExpr::Init(init) => init.translate(translator),
+            HirExpr::Layout(layout) => layout.translate(translator),
+            HirExpr::Is(is) => is.translate(translator),
+            HirExpr::Cast(cast) => cast.translate(translator),
+            HirExpr::Convert(convert) => convert.translate(translator),
+            HirExpr::Closure(closure) => closure.translate(translator),
+            // Only the first element is kept when a tuple is used for its own sake
+            HirExpr::Tuple(_) | HirExpr::Element(_) | HirExpr::Wrap(_) | HirExpr::Try(_) => {
+                let (stmts, leaves) = translator.tuple(self);
+                sequence(&stmts, leaves[0].clone())
+            }
         }
     }
 }
 
-impl Translate<Stmt> for AstStmt {
+impl Translate<Stmt> for HirStmt {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
         match self {
-            AstStmt::If(c) => c.translate(translator),
-            AstStmt::While(w) => w.translate(translator),
-            AstStmt::For(f) => f.translate(translator),
-            AstStmt::Assign(a) => a.translate(translator),
-            AstStmt::Expr(e) => e.translate(translator),
-            AstStmt::Return(r) => r.translate(translator),
-            AstStmt::Var(v) => v.translate(translator),
+            HirStmt::If(c) => c.translate(translator),
+            HirStmt::While(w) => w.translate(translator),
+            HirStmt::For(f) => f.translate(translator),
+            HirStmt::Assign(a) => a.translate(translator),
+            HirStmt::Expr(e) => e.translate(translator),
+            HirStmt::Return(r) => r.translate(translator),
+            HirStmt::Var(v) => v.translate(translator),
+            HirStmt::Destructure(d) => d.translate(translator),
         }
     }
 }
 
-impl Translate<Vec<Stmt>> for AstDecl {
+impl Translate<Vec<Stmt>> for HirDecl {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Vec<Stmt> {
         match self {
-            AstDecl::Function(function) => vec![function.translate(translator)],
-            AstDecl::Class(cls) => cls.translate(translator),
-            AstDecl::Constant(_) => todo!(),
+            // External functions are provided by the runtime
+            HirDecl::Function(function) if function.external => vec![],
+            HirDecl::Function(function) => vec![function.translate(translator)],
+            HirDecl::Class(cls) => cls.translate(translator),
         }
     }
 }
 
-impl Translate<Expr> for ast::node::Literal<&str> {
+impl Translate<Expr> for hir::node::Literal<&str> {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
         Expr::ConstStr(
             translator
                 .ctx
                 .constants
-                .add(vec![self.value.to_string().replace('"', "")]),
+                .add(vec![self.contents().to_string()]),
         )
     }
 }
 
-impl Translate<Expr> for ast::node::Literal<i64> {
+impl Translate<Expr> for hir::node::Literal<i64> {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, _: &mut Translator<I, F>) -> Expr {
         Const::<i64>::int(self.value)
     }
 }
 
-impl Translate<Expr> for ast::node::Literal<f64> {
+impl Translate<Expr> for hir::node::Literal<f64> {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, _: &mut Translator<I, F>) -> Expr {
         Const::<f64>::float(self.value)
     }
 }
 
-impl Translate<Expr> for ast::node::Literal<bool> {
+impl Translate<Expr> for hir::node::Literal<bool> {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, _: &mut Translator<I, F>) -> Expr {
         Const::<i64>::int(self.value.into())
     }
 }
 
-impl Translate<Expr> for ast::node::Binary {
+impl Translate<Expr> for hir::node::Literal<char> {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, _: &mut Translator<I, F>) -> Expr {
+        Const::<i64>::int(u32::from(self.value).into())
+    }
+}
+
+impl Translate<Expr> for hir::node::Binary {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        let expr = self.operation(translator);
+        match expr.relation() {
+            Some(rel) if matches!(expr, Expr::Binary(_)) => {
+                Self::materialize::<I, F>(expr, rel, translator)
+            }
+            _ => expr,
+        }
+    }
+}
+
+impl hir::node::Binary {
+    /// Translates the operation itself. A comparison only sets the condition flags, so it can be
+    /// used directly as the condition of a jump.
+    fn operation<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
         let bin = self.fold();
-        match bin {
-            AstExpr::Binary(_) => Expr::checked_binary(
-                self.op.kind.into(),
-                self.left.translate(translator),
-                self.right.translate(translator),
-            ),
-            _ => bin.translate(translator),
+        if !matches!(bin, HirExpr::Binary(_)) {
+            return bin.translate(translator);
         }
+        let ty = self.left.ty();
+        let op = BinOp::from(self.op);
+        let op = if ty.is_unsigned() {
+            op.unsigned()
+        } else if ty.is_float() {
+            op.float(ty.bits())
+        } else {
+            op
+        };
+        let expr = Expr::checked_binary(
+            op,
+            self.left.translate(translator),
+            self.right.translate(translator),
+        );
+        if op.relation().is_some() {
+            expr
+        } else {
+            extend(expr, &ty)
+        }
+    }
+
+    /// Stores the outcome of `comparison` as a boolean in a temporary, returning the temporary.
+    fn materialize<I: ArchInstr, F: Frame<I>>(
+        comparison: Expr,
+        rel: RelOp,
+        translator: &mut Translator<I, F>,
+    ) -> Expr {
+        let id = translator.function.unwrap();
+        let frame = translator.functions.get_mut(&id).unwrap();
+        let saved = frame.allocate(&Temp::next(), false);
+        let (t, f, done) = (Label::next(), Label::next(), Label::next());
+        let stmts = [
+            CJump::wrapped(BinOp::Cmp(rel), comparison, t.clone(), f.clone()),
+            Label::wrapped(t),
+            Stmt::checked_move(saved.clone(), Const::<i64>::int(1)),
+            Jump::wrapped(done.clone()),
+            Label::wrapped(f),
+            Stmt::checked_move(saved.clone(), Const::<i64>::int(0)),
+            Label::wrapped(done),
+        ];
+        ESeq::wrapped(Stmt::from(&stmts[..]), saved)
     }
 }
 
-impl Translate<Expr> for ast::node::Call {
+impl Translate<Expr> for hir::node::Call {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        let (stmts, mut saved) = self.invoke(translator);
+        ESeq::wrapped(Stmt::from(&stmts[..]), saved.remove(0))
+    }
+}
+
+impl hir::node::Call {
+    /// Translates the call, returning the statements that make it along with the frame slots its
+    /// result is saved in. A tuple is returned in the argument registers, one element to each.
+    fn invoke<I: ArchInstr, F: Frame<I>>(
+        &self,
+        translator: &mut Translator<I, F>,
+    ) -> (Vec<Stmt>, Vec<Expr>) {
         let r = F::registers();
-        let args: Vec<_> = self
-            .args
-            .iter()
-            .map(|arg| arg.translate(translator))
-            .collect();
-        let mut cls = None;
-        let name = match *self.left {
-            AstExpr::Ident(ref ident) => ident.name.to_string(),
-            AstExpr::Access(ref access) => {
-                let meta = translator.meta.access.get(&access.id).unwrap();
-                let name = meta.symbols.iter().rev().take(2).rev().map(|item| match item {
-                    Symbol::Function(fun) => fun.name.to_string(),
-                    Symbol::Class(c) => {
-                        cls = Some(c);
-                        c.name.to_string()
-                    }
-                    _ => unimplemented!(),
-                }).fold(String::new(), |acc, item| format!("{acc}{item}."));
-                name.trim_end_matches('.').to_string()
-            },
-            _ => panic!("Expected either `AstExpr::Ident` or `AstExpr::Access` on left side of call expression"),
+        // A function value is evaluated before its arguments
+        let closure = match &self.callee {
+            Callee::Closure(closure) => Some(closure.translate(translator)),
+            _ => None,
         };
-        let ptr = match *self.left {
-            AstExpr::Ident(_) => translator.symbols[&name].is_ptr(),
-            AstExpr::Access(ref access) => {
-                let meta = translator.meta.access.get(&access.id).unwrap();
-                meta.symbols.last().unwrap().is_ptr()
+        let mut args: Vec<_> = closure.into_iter().collect();
+        for arg in &self.args {
+            if arg.ty().elements().is_some() {
+                let (stmts, leaves) = translator.tuple(arg);
+                let mut leaves = leaves.into_iter();
+                args.push(sequence(&stmts, leaves.next().unwrap()));
+                args.extend(leaves);
+            } else {
+                args.push(arg.translate(translator));
             }
-            _ => unimplemented!(),
-        };
-        let temp = Temp::next();
+        }
         let id = translator.function.unwrap();
         let frame = translator.functions.get_mut(&id).unwrap();
-        let saved = frame.allocate(&temp, ptr);
+        let saved: Vec<_> = self
+            .ty
+            .leaves()
+            .iter()
+            .map(|(_, ty)| frame.allocate(&Temp::next(), ty.is_ptr()))
+            .collect();
+        if let Callee::Function(name) = &self.callee {
+            if builtins::ALLOCATING.contains(&name.as_str()) {
+                args.append(&mut vec![
+                    Temp::wrapped(r.frame.to_string()),
+                    Const::<i64>::int(
+                        frame.offset().sub(
+                            i64::try_from((self.args.len() * 2 + 1) * F::word_size()).unwrap(),
+                        ),
+                    ),
+                ]);
+            }
+        }
         let mut stmts = vec![];
-        let address = if cls.is_some_and(|cls| {
-            Symbol::has_subclass(cls, translator.symbols)
-                || !Symbol::superclasses(cls, translator.symbols).is_empty()
-        }) {
-            // This call could be overridden by a subclass in which case we need to use dynamic dispatch.
-            // we just checked is_some_and, so this is safe.
-            let cls = cls.unwrap();
-            let descriptor: &Expr = args.first().unwrap();
-            let arr = Temp::next();
-            let address = Temp::next();
-            let (_, n) = name.rsplit_once('.').unwrap();
-            let symbol = Symbol::Class(cls.clone());
-            let index = F::word_size()
-                * (symbol
-                    .methods(translator.symbols)
+        let address = match &self.callee {
+            Callee::Function(name) | Callee::Static(name) => name.clone(),
+            Callee::Method { class, name } => {
+                let symbol = Symbol::Class(Rc::clone(class));
+                let methods = symbol.methods(translator.symbols);
+                if Symbol::has_subclass(class, translator.symbols)
+                    || !Symbol::superclasses(class, translator.symbols).is_empty()
+                {
+                    // This call could be overridden by a subclass in which case we need to use dynamic dispatch.
+                    let slot = translator.interfaces.len()
+                        + methods.iter().position(|(_, m)| m.name == **name).unwrap();
+                    Self::dispatch::<I, F>(&mut args, slot, &mut stmts)
+                } else {
+                    methods
+                        .into_iter()
+                        .find(|(_, m)| m.name == **name)
+                        .map(|(label, _)| label)
+                        .unwrap()
+                }
+            }
+            Callee::Interface { interface, name } => {
+                let slot = translator
+                    .interfaces
                     .iter()
-                    .position(|(_, m)| m.name == n)
-                    .unwrap()
-                    + 1);
-            stmts.append(&mut vec![
-                Move::wrapped(
-                    Temp::wrapped(arr.clone()),
-                    Mem::wrapped(descriptor.clone(), F::word_size().try_into().unwrap()),
-                ),
-                Stmt::checked_move(
-                    Temp::wrapped(address.clone()),
-                    Mem::wrapped(Temp::wrapped(arr.clone()), index.try_into().unwrap()),
-                ),
-            ]);
-            address
-        } else {
-            name
+                    .position(|(i, m)| interface.name == **i && m == name)
+                    .unwrap();
+                Self::dispatch::<I, F>(&mut args, slot, &mut stmts)
+            }
+            Callee::Closure(_) => Self::indirect::<I, F>(&mut args, &mut stmts),
         };
+        stmts.push(Stmt::Expr(Box::new(Call::wrapped(address, args))));
+        if self.ty.elements().is_none() {
+            stmts.push(Move::wrapped(saved[0].clone(), Temp::wrapped(r.ret.into())));
+        } else {
+            for (saved, register) in saved.iter().zip(r.argument) {
+                stmts.push(Move::wrapped(
+                    saved.clone(),
+                    Temp::wrapped((*register).into()),
+                ));
+            }
+        }
+        (stmts, saved)
+    }
+}
+
+impl hir::node::Call {
+    /// Loads the method in `slot` of the receiver's method array into a temporary, returning the
+    /// temporary. The receiver is evaluated once, since it is needed both to find the method and
+    /// as the first argument.
+    fn dispatch<I: ArchInstr, F: Frame<I>>(
+        args: &mut Vec<Expr>,
+        slot: usize,
+        stmts: &mut Vec<Stmt>,
+    ) -> String {
+        let receiver = Temp::next();
+        let arr = Temp::next();
+        let address = Temp::next();
+        let index = F::word_size() * (slot + 1);
         stmts.append(&mut vec![
-            Stmt::Expr(Box::new(Call::wrapped(address, args))),
-            Move::wrapped(saved.clone(), Temp::wrapped(r.ret.into())),
+            Stmt::checked_move(Temp::wrapped(receiver.clone()), args.remove(0)),
+            Move::wrapped(
+                Temp::wrapped(arr.clone()),
+                Mem::wrapped(
+                    Temp::wrapped(receiver.clone()),
+                    F::word_size().try_into().unwrap(),
+                ),
+            ),
+            Stmt::checked_move(
+                Temp::wrapped(address.clone()),
+                Mem::wrapped(Temp::wrapped(arr), index.try_into().unwrap()),
+            ),
         ]);
-        ESeq::wrapped(Stmt::from(&stmts[..]), saved)
+        args.insert(0, Temp::wrapped(receiver));
+        address
     }
 }
 
-impl Translate<Expr> for ast::node::Ident {
+impl hir::node::Call {
+    /// Loads the function held by the closure in the first argument into a temporary, returning
+    /// the temporary. The closure remains the first argument, since the function reads the
+    /// variables it captured from it.
+    fn indirect<I: ArchInstr, F: Frame<I>>(args: &mut Vec<Expr>, stmts: &mut Vec<Stmt>) -> String {
+        let closure = Temp::next();
+        let address = Temp::next();
+        stmts.append(&mut vec![
+            Stmt::checked_move(Temp::wrapped(closure.clone()), args.remove(0)),
+            Stmt::checked_move(
+                Temp::wrapped(address.clone()),
+                Mem::wrapped(
+                    Temp::wrapped(closure.clone()),
+                    F::word_size().try_into().unwrap(),
+                ),
+            ),
+        ]);
+        args.insert(0, Temp::wrapped(closure));
+        address
+    }
+}
+
+impl Translate<Expr> for hir::node::Closure {
+    /// A closure is laid out like an object: the function takes the place of the method array,
+    /// and the captured values take the place of the fields.
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
-        translator.frame().get(&self.name.to_string())
+        let r = F::registers();
+        let id = translator.function.unwrap();
+        let frame = translator.functions.get_mut(&id).unwrap();
+        let base = frame.allocate(&Temp::next(), true);
+        let offset = frame
+            .offset()
+            .sub(i64::try_from((self.captures.len() * 2 + 1) * F::word_size()).unwrap());
+        let descriptor = translator.ctx.constants.add(vec![self.descriptor()]);
+        let temp = Temp::next();
+        let mut stmts = vec![
+            Stmt::Expr(Box::new(Call::wrapped(
+                "alloc".into(),
+                vec![
+                    Expr::ConstStr(descriptor),
+                    Temp::wrapped(r.frame.to_string()),
+                    Const::<i64>::int(offset),
+                ],
+            ))),
+            Stmt::checked_move(base.clone(), Temp::wrapped(r.ret.to_string())),
+            Stmt::checked_move(Temp::wrapped(temp.clone()), base.clone()),
+            Move::wrapped(
+                Mem::wrapped(
+                    Temp::wrapped(temp.clone()),
+                    F::word_size().try_into().unwrap(),
+                ),
+                Expr::ConstLabel(self.function.clone()),
+            ),
+            // Closures have no class, so they never pass a runtime type test
+            Move::wrapped(
+                Mem::wrapped(
+                    Temp::wrapped(temp.clone()),
+                    (runtime::CLASS_ID_FIELD * F::word_size())
+                        .try_into()
+                        .unwrap(),
+                ),
+                Const::<i64>::int(0),
+            ),
+        ];
+        for (i, capture) in self.captures.iter().enumerate() {
+            let value = capture.translate(translator);
+            let offset = ((i + runtime::CLASS_METADATA_FIELDS) * F::word_size())
+                .try_into()
+                .unwrap();
+            stmts.push(Stmt::checked_move(
+                Mem::wrapped(Temp::wrapped(temp.clone()), offset),
+                value,
+            ));
+        }
+        ESeq::wrapped(Stmt::from(&stmts[..]), base)
     }
 }
 
-impl Translate<Expr> for ast::node::Unary {
+impl Translate<Expr> for hir::node::Layout {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
-        match self.op.kind {
-            Kind::Minus => Binary::wrapped(
+        // The IR has no bitwise operations, so the bits are put together with arithmetic
+        let mut known = 0;
+        let mut layout = None;
+        for (k, bit) in self.bits.iter().enumerate() {
+            let place = 1 << k;
+            let (source, index) = match bit {
+                hir::node::Bit::Known(pointer) => {
+                    known += i64::from(*pointer) * place;
+                    continue;
+                }
+                hir::node::Bit::Param(types, index) => (types.translate(translator), *index),
+                hir::node::Bit::Object(object, index) => {
+                    let temp = Temp::next();
+                    let offset = (runtime::LAYOUT_FIELD * F::word_size()).try_into().unwrap();
+                    let layout = ESeq::wrapped(
+                        Stmt::checked_move(
+                            Temp::wrapped(temp.clone()),
+                            object.translate(translator),
+                        ),
+                        Mem::wrapped(Temp::wrapped(temp), offset),
+                    );
+                    (layout, *index)
+                }
+            };
+            let term = Expr::checked_binary(
+                BinOp::Mul,
+                Self::bit(source, index),
+                Const::<i64>::int(place),
+            );
+            layout = Some(match layout {
+                Some(layout) => Expr::checked_binary(BinOp::Plus, layout, term),
+                None => term,
+            });
+        }
+        match layout {
+            Some(layout) if known != 0 => {
+                Expr::checked_binary(BinOp::Plus, layout, Const::<i64>::int(known))
+            }
+            Some(layout) => layout,
+            None => Const::<i64>::int(known),
+        }
+    }
+}
+
+impl hir::node::Layout {
+    /// Bit `index` of the layout `source`, as `source / 2^index - source / 2^(index + 1) * 2`.
+    /// An operation leaves its result in its left operand, so each division gets its own copy.
+    fn bit(source: Expr, index: usize) -> Expr {
+        let (low, high) = (Temp::next(), Temp::next());
+        let shifted = |temp: &String, by: usize| {
+            Expr::checked_binary(
+                BinOp::Div,
+                Temp::wrapped(temp.clone()),
+                Const::<i64>::int(1 << by),
+            )
+        };
+        let stmts = [
+            Stmt::checked_move(Temp::wrapped(low.clone()), source),
+            Move::wrapped(Temp::wrapped(high.clone()), Temp::wrapped(low.clone())),
+        ];
+        ESeq::wrapped(
+            Stmt::from(&stmts[..]),
+            Expr::checked_binary(
                 BinOp::Minus,
+                shifted(&low, index),
+                Expr::checked_binary(BinOp::Mul, shifted(&high, index + 1), Const::<i64>::int(2)),
+            ),
+        )
+    }
+}
+
+impl Translate<Expr> for hir::node::Is {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        let (first, last) = translator.class_ids[&self.class];
+        let object = self.expr.translate(translator);
+        let args = vec![object, Const::<i64>::int(first), Const::<i64>::int(last)];
+        translator.runtime_call("instance_of", args, false)
+    }
+}
+
+impl Translate<Expr> for hir::node::Cast {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        let class = self.ty.to_string();
+        let (first, last) = translator.class_ids[&class];
+        let object = self.expr.translate(translator);
+        // The name of the class is only needed to report a failed cast
+        let name = translator.ctx.constants.add(vec![class]);
+        let args = vec![
+            object,
+            Const::<i64>::int(first),
+            Const::<i64>::int(last),
+            Expr::ConstStr(name),
+        ];
+        translator.runtime_call("checked_cast", args, true)
+    }
+}
+
+impl Translate<Expr> for hir::node::Convert {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        // A character is held like the unsigned integer of its scalar value
+        let integer = |ty: Type| match ty {
+            Type::Char => Type::Integer(32, false),
+            ty => ty,
+        };
+        let (from, to) = (integer(self.expr.ty()), integer(self.ty.clone()));
+        let signed = |ty: &Type| !ty.is_unsigned();
+        if let (HirExpr::Int(literal), true) = (&self.expr, to.is_integer()) {
+            return Const::<i64>::int(wrap(literal.value, &to));
+        }
+        let expr = self.expr.translate(translator);
+        match (from.is_integer(), to.is_integer()) {
+            (true, true) => extend(expr, &to),
+            (true, false) => Convert::wrapped(
+                Conversion::IntToFloat {
+                    signed: signed(&from),
+                    bits: to.bits(),
+                },
+                expr,
+            ),
+            (false, true) => extend(
+                Convert::wrapped(
+                    Conversion::FloatToInt {
+                        signed: signed(&to),
+                        bits: from.bits(),
+                    },
+                    expr,
+                ),
+                &to,
+            ),
+            (false, false) => Convert::wrapped(
+                Conversion::Float {
+                    from: from.bits(),
+                    to: to.bits(),
+                },
+                expr,
+            ),
+        }
+    }
+}
+
+/// Wraps the result of integer arithmetic to the width of `ty`, since values are held in whole
+/// registers.
+fn extend(expr: Expr, ty: &Type) -> Expr {
+    match ty {
+        Type::Integer(bits, signed) if *bits < 64 => Convert::wrapped(
+            Conversion::Extend {
+                bits: *bits,
+                signed: *signed,
+            },
+            expr,
+        ),
+        _ => expr,
+    }
+}
+
+/// The value of `value` converted to the integer type `ty`, as held in a whole register.
+fn wrap(value: i64, ty: &Type) -> i64 {
+    match ty {
+        Type::Integer(bits, signed) if *bits < 64 => {
+            let shift = 64 - bits;
+            if *signed {
+                (value << shift) >> shift
+            } else {
+                i64::try_from(u64::from_ne_bytes((value << shift).to_ne_bytes()) >> shift).unwrap()
+            }
+        }
+        _ => value,
+    }
+}
+
+impl Translate<Expr> for hir::node::Ident {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        if self.ty.elements().is_some() {
+            let (path, _) = self.ty.leaves().remove(0);
+            return translator.frame().get(&format!("{}{path}", self.name));
+        }
+        translator.frame().get(&self.name)
+    }
+}
+
+impl Translate<Expr> for hir::node::Unary {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
+        let unary = self.fold();
+        if !matches!(unary, HirExpr::Unary(_)) {
+            return unary.translate(translator);
+        }
+        match self.op {
+            // The bits of the integer 0 are those of the float 0
+            Kind::Minus if self.ty.is_float() => Binary::wrapped(
+                BinOp::Minus.float(self.ty.bits()),
                 Const::<i64>::int(0),
                 self.expr.translate(translator),
             ),
+            Kind::Minus => extend(
+                Binary::wrapped(
+                    BinOp::Minus,
+                    Const::<i64>::int(0),
+                    self.expr.translate(translator),
+                ),
+                &self.ty,
+            ),
             Kind::Bang => Binary::wrapped(
                 BinOp::Xor,
                 self.expr.translate(translator),
@@ -260,68 +854,85 @@ impl Translate<Expr> for ast::node::Unary {
     }
 }
 
-impl Translate<Expr> for ast::node::Access {
+impl hir::node::Access {
+    /// Loads the object being accessed into a temporary, returning the temporary along with the
+    /// statement that loads it. The field then lives at `Mem(temp, self.offset())`.
+    fn object<I: ArchInstr, F: Frame<I>>(
+        &self,
+        translator: &mut Translator<I, F>,
+    ) -> (Stmt, String) {
+        let object = self.object.translate(translator);
+        let temp = Temp::next();
+        (
+            Stmt::checked_move(Temp::wrapped(temp.clone()), object),
+            temp,
+        )
+    }
+
+    /// The location of the field in the object held by `temp`.
+    fn field<I: ArchInstr, F: Frame<I>>(
+        &self,
+        temp: String,
+        translator: &Translator<I, F>,
+    ) -> Expr {
+        let class = self.object.ty().class().unwrap().name.to_string();
+        let (offset, narrow) = translator.field(&class, self.field)[0];
+        Mem::narrow(Temp::wrapped(temp), offset, narrow)
+    }
+
+    /// Loads the object being accessed, returning the statement that loads it along with the
+    /// location of each leaf of the field, which is a struct embedded in the object.
+    fn places<I: ArchInstr, F: Frame<I>>(
+        &self,
+        translator: &mut Translator<I, F>,
+    ) -> (Stmt, Vec<Expr>) {
+        let (load, temp) = self.object(translator);
+        let class = self.object.ty().class().unwrap().name.to_string();
+        let places = translator.field(&class, self.field).into_iter();
+        let places =
+            places.map(|(offset, narrow)| Mem::narrow(Temp::wrapped(temp.clone()), offset, narrow));
+        (load, places.collect())
+    }
+}
+
+impl hir::node::Element {
+    /// The range of the leaves of the tuple that make up this element.
+    fn leaves(&self) -> std::ops::Range<usize> {
+        let Some(elements) = self.tuple.ty().elements() else {
+            unreachable!("only tuples have elements")
+        };
+        let start = elements[..self.index]
+            .iter()
+            .map(|ty| ty.leaves().len())
+            .sum::<usize>();
+        start..start + elements[self.index].leaves().len()
+    }
+}
+
+impl Translate<Expr> for hir::node::Access {
     // heh, this is basically the spiritual equivalent of LLVM's getelementptr
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
-        let meta = translator
-            .meta
-            .access
-            .get(&self.id)
-            .unwrap_or_else(|| panic!("expected metadata for access node {}", self.id));
-        let head = self.chain.first().unwrap();
-        let mut initial = vec![];
-        let ident = match head {
-            AstExpr::Ident(ident) => ident.name.to_string(),
-            AstExpr::Init(init) => {
-                // Invent an "anonymous" variable to hold the value of the initializer
-                let name = Temp::next();
-                let decl = ast::node::VarDecl::wrapped(
-                    Token::new(Kind::Identifier, Some(name.clone().leak()), Span::default()),
-                    Type::new(
-                        Token::new(Kind::Literal, init.name.lexeme, Span::default()),
-                        vec![],
-                    ),
-                    head.clone(),
-                );
-                let stmt = decl.translate(translator);
-                initial.push(stmt);
-                name
-            }
-            _ => unimplemented!(),
-        };
-        let frame = translator.frame();
-        let base = frame.get(&ident);
-        let temp = Temp::next();
-        initial.append(&mut vec![Stmt::checked_move(
-            Temp::wrapped(temp.clone()),
-            base,
-        )]);
-        let stmts: Vec<_> = initial
-            .into_iter()
-            .chain(meta.indices.iter().map(|&field| {
-                let offset: i64 = ((field + runtime::CLASS_METADATA_FIELDS) * F::word_size())
-                    .try_into()
-                    .unwrap();
-                let mem = Mem::new(Box::new(Temp::wrapped(temp.clone())), offset);
-                translator.ctx.mem = Some(mem.clone());
-                Stmt::checked_move(Temp::wrapped(temp.clone()), Expr::Mem(mem))
-            }))
-            .collect();
-        translator.ctx.stmts = stmts.clone();
+        let (load, temp) = self.object(translator);
+        let field = self.field(temp.clone(), translator);
+        let stmts = [load, Stmt::checked_move(Temp::wrapped(temp.clone()), field)];
         ESeq::wrapped(Stmt::from(&stmts[..]), Temp::wrapped(temp))
     }
 }
 
-impl Translate<Expr> for ast::node::Init {
+impl Translate<Expr> for hir::node::Init {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Expr {
         let r = F::registers();
+        let class = self.ty.class().unwrap();
+        let method_descriptor = translator.method_array(class);
         let id = translator.function.unwrap();
         let frame = translator.functions.get_mut(&id).unwrap();
         let name = translator.ctx.name.join(".");
         let base = frame.allocate(&name, true);
-        let cls = &translator.symbols[&self.name.to_string()];
+        // Each instantiation of a generic class has its own layout
+        let descriptor =
+            translator.classes[&class.name.to_string()].descriptor(&self.ty.arguments());
+        let cls = Symbol::Class(Rc::clone(class));
         let fields = cls.fields(translator.symbols);
-        let (descriptor, method_descriptor) = cls.descriptor(translator.symbols);
         let ptr = translator.ctx.constants.add(vec![descriptor]);
         let array_ptr = translator
             .ctx
@@ -359,20 +970,7 @@ impl Translate<Expr> for ast::node::Init {
                 Temp::wrapped(r.ret.to_string()),
             ),
         ];
-        // Initialize the method descriptor array
-        for (i, method) in method_descriptor.iter().enumerate() {
-            let offset: i64 = ((i + 1) * F::word_size()).try_into().unwrap();
-            setup.push(Move::wrapped(
-                Mem::wrapped(Temp::wrapped(array.clone()), offset),
-                Expr::ConstLabel(method.clone()),
-            ));
-        }
-        // Initialize fields in the correct order
-        let mut initializers: Vec<_> = self.initializers.iter().collect();
-        initializers.sort_by(|a, b| {
-            let position = |name: &Token| fields.iter().position(|f| f.name == *name);
-            position(&a.name).cmp(&position(&b.name))
-        });
+        setup.extend(Self::methods::<I, F>(&array, &method_descriptor));
         let temp = Temp::next();
         // Hold a pointer to the method descriptor array at class_ptr[8]
         setup.append(&mut vec![
@@ -384,37 +982,129 @@ impl Translate<Expr> for ast::node::Init {
                 ),
                 Temp::wrapped(array),
             ),
+            // The class ID is used to test the type of the object at runtime
+            Move::wrapped(
+                Mem::wrapped(
+                    Temp::wrapped(temp.clone()),
+                    (runtime::CLASS_ID_FIELD * F::word_size())
+                        .try_into()
+                        .unwrap(),
+                ),
+                Const::<i64>::int(translator.class_ids[&class.name.to_string()].0),
+            ),
+            // The GC needs the type layout to tell which fields of a generic class hold pointers
+            Stmt::checked_move(
+                Mem::wrapped(
+                    Temp::wrapped(temp.clone()),
+                    (runtime::LAYOUT_FIELD * F::word_size()).try_into().unwrap(),
+                ),
+                self.layout.translate(translator),
+            ),
         ]);
-        // Initialize class fields
-        let stmts: Vec<_> = setup
-            .into_iter()
-            .chain(initializers.iter().enumerate().map(|(i, init)| {
-                translator.ctx.name.push(init.name.to_string());
-                let value = match &init.expr {
-                    AstExpr::Init(init) => init.translate(translator),
-                    _ => init.expr.translate(translator),
-                };
-                let offset = ((i + runtime::CLASS_METADATA_FIELDS) * F::word_size())
-                    .try_into()
-                    .unwrap();
-                translator.ctx.name.pop();
-                Stmt::checked_move(Mem::wrapped(Temp::wrapped(temp.clone()), offset), value)
-            }))
-            .collect();
+        // Initialize class fields (the type checker already ordered the initializers by field)
+        let mut stmts = setup;
+        for (i, init) in self.initializers.iter().enumerate() {
+            translator
+                .ctx
+                .name
+                .push(fields[init.field].name.to_string());
+            let places = translator.field(&class.name.to_string(), i);
+            stmts.append(&mut Self::store(
+                translator, &init.expr, places, &temp, &base,
+            ));
+            translator.ctx.name.pop();
+        }
+        // The object is only handed to its `init` method once every field holds a value
+        if let Some(init) = &self.method {
+            stmts.push(Stmt::Expr(Box::new(Call::wrapped(
+                init.clone(),
+                vec![base.clone()],
+            ))));
+        }
         ESeq::wrapped(Stmt::from(&stmts[..]), base)
     }
 }
 
-impl Translate<Stmt> for ast::node::If {
+impl hir::node::Init {
+    /// Initializes the method descriptor array held by `array` with the label of each method
+    fn methods<I: ArchInstr, F: Frame<I>>(array: &str, methods: &[Option<String>]) -> Vec<Stmt> {
+        methods
+            .iter()
+            .enumerate()
+            .map(|(i, method)| {
+                let offset: i64 = ((i + 1) * F::word_size()).try_into().unwrap();
+                let method = method.as_ref().map_or_else(
+                    || Const::<i64>::int(0),
+                    |label| Expr::ConstLabel(label.clone()),
+                );
+                Move::wrapped(
+                    Mem::wrapped(Temp::wrapped(array.to_string()), offset),
+                    method,
+                )
+            })
+            .collect()
+    }
+
+    /// Stores `expr` in the field at `places` of the object held by `temp`. A struct is stored in
+    /// place, one leaf at a time, and the object is found again from `base` once the struct has
+    /// been evaluated, since evaluating it may allocate.
+    fn store<I: ArchInstr, F: Frame<I>>(
+        translator: &mut Translator<I, F>,
+        expr: &HirExpr,
+        places: Vec<(i64, Option<Narrow>)>,
+        temp: &str,
+        base: &Expr,
+    ) -> Vec<Stmt> {
+        let field = |(offset, narrow)| Mem::narrow(Temp::wrapped(temp.to_string()), offset, narrow);
+        if expr.ty().elements().is_none() {
+            let value = expr.translate(translator);
+            return vec![Stmt::checked_move(field(places[0]), value)];
+        }
+        let (mut stmts, leaves) = translator.tuple(expr);
+        stmts.push(Stmt::checked_move(
+            Temp::wrapped(temp.to_string()),
+            base.clone(),
+        ));
+        for (place, leaf) in places.into_iter().zip(leaves) {
+            let value = Temp::next();
+            stmts.push(Stmt::checked_move(Temp::wrapped(value.clone()), leaf));
+            stmts.push(Stmt::checked_move(field(place), Temp::wrapped(value)));
+        }
+        stmts
+    }
+}
+
+/// Translates the condition of a branch into a comparison that a conditional jump can test.
+/// Conditions that are not already comparisons (such as calls) are compared against zero.
+fn condition<I: ArchInstr, F: Frame<I>>(
+    condition: &HirExpr,
+    translator: &mut Translator<I, F>,
+) -> Expr {
+    match condition {
+        HirExpr::Int(i) => Binary::wrapped(
+            BinOp::Cmp(RelOp::Equal),
+            Const::<i64>::int(i.value),
+            Const::<i64>::int(0),
+        ),
+        c => {
+            let condition: Expr = match c {
+                HirExpr::Binary(binary) => binary.operation(translator),
+                c => c.translate(translator),
+            };
+            if condition.relation().is_some() {
+                condition
+            } else {
+                Binary::wrapped(BinOp::Cmp(RelOp::NotEqual), condition, Const::<i64>::int(0))
+            }
+        }
+    }
+}
+
+impl Translate<Stmt> for hir::node::If {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
-        let condition = match &self.condition {
-            AstExpr::Int(i) => Binary::wrapped(
-                BinOp::Cmp(RelOp::Equal),
-                Const::<i64>::int(i.value),
-                Const::<i64>::int(0),
-            ),
-            c => c.translate(translator),
-        };
+        let condition = condition(&self.condition, translator);
+        // Only a return within this branch should send it to the epilogue, not one from earlier on
+        translator.ctx.ret = false;
         let is: Vec<Stmt> = self
             .is
             .iter()
@@ -458,16 +1148,9 @@ impl Translate<Stmt> for ast::node::If {
     }
 }
 
-impl Translate<Stmt> for ast::node::While {
+impl Translate<Stmt> for hir::node::While {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
-        let condition = match &self.condition {
-            AstExpr::Int(i) => Binary::wrapped(
-                BinOp::Cmp(RelOp::Equal),
-                Const::<i64>::int(i.value),
-                Const::<i64>::int(0),
-            ),
-            c => c.translate(translator),
-        };
+        let condition = condition(&self.condition, translator);
         let t = Label::next();
         let f = Label::next();
         let test = Label::next();
@@ -496,73 +1179,69 @@ impl Translate<Stmt> for ast::node::While {
     }
 }
 
-impl Translate<Stmt> for ast::node::For {
+impl Translate<Stmt> for hir::node::For {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
-        let range = self.iter.range();
-        let cur = ast::node::Ident::wrapped(self.index.clone());
-        let start = ast::node::VarDecl::wrapped(
-            cur.clone().ident().name.clone(),
-            Type::new(
-                Token::new(Kind::Literal, Some("int"), Span::default()),
-                vec![],
-            ),
-            range.start.clone(),
-        );
-        let w = ast::node::While {
-            condition: ast::node::Binary::wrapped(
-                cur.clone(),
-                Token::new(Kind::LessEqual, None, Span::default()),
-                range.end.clone(),
-            ),
-            body: self
-                .body
-                .clone()
+        let lowered = self.lowered();
+        let w = hir::node::While {
+            condition: lowered.condition,
+            body: lowered
+                .enter
                 .into_iter()
-                .chain([ast::node::Assign::wrapped(
-                    cur.clone(),
-                    ast::node::Binary::wrapped(
-                        cur,
-                        Token::new(Kind::Plus, None, Span::default()),
-                        ast::node::Literal::<i64>::int(
-                            1,
-                            Token::new(Kind::Literal, Some("1"), Span::default()),
-                        ),
-                    ),
-                )])
+                .chain(self.body.iter().cloned())
+                .chain(lowered.advance)
                 .collect(),
         };
-        let stmts: Vec<Stmt> = vec![start.translate(translator), w.translate(translator)];
+        let mut stmts: Vec<Stmt> = lowered
+            .setup
+            .iter()
+            .map(|stmt| stmt.translate(translator))
+            .collect();
+        stmts.push(w.translate(translator));
         Stmt::from(&stmts[..])
     }
 }
 
-impl Translate<Stmt> for ast::node::Assign {
+impl Translate<Stmt> for hir::node::Assign {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
-        let target: Expr = self.target.translate(translator);
-        translator.ctx.stmts.pop();
-        let target = if matches!(self.target, AstExpr::Access(_)) {
-            ESeq::wrapped(
-                Stmt::from(&translator.ctx.stmts[..]),
-                Expr::Mem(translator.ctx.mem.take().unwrap()),
-            )
-        } else {
-            target
+        if self.target.ty().elements().is_some() || matches!(self.target, HirExpr::Element(_)) {
+            // The value is saved before the target is found, since finding a struct embedded in
+            // an object loads the object
+            let (mut stmts, values) = translator.element(&self.expr);
+            let (mut load, targets) = translator.places(&self.target);
+            stmts.append(&mut load);
+            for (target, value) in targets.into_iter().zip(values) {
+                stmts.push(Stmt::checked_move(target, value));
+            }
+            return Stmt::from(&stmts[..]);
+        }
+        let target = match &self.target {
+            HirExpr::Access(access) => {
+                let (load, temp) = access.object(translator);
+                ESeq::wrapped(load, access.field(temp, translator))
+            }
+            target => target.translate(translator),
         };
-        translator.ctx.stmts.clear();
         Stmt::checked_move(target, self.expr.translate(translator))
     }
 }
 
-impl Translate<Stmt> for AstExpr {
+impl Translate<Stmt> for HirExpr {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
         Stmt::Expr(Box::new(self.translate(translator)))
     }
 }
 
-impl Translate<Stmt> for ast::node::Return {
+impl Translate<Stmt> for hir::node::Return {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
         let r = F::registers();
         translator.ctx.ret = true;
+        if self.expr.ty().elements().is_some() {
+            let (mut stmts, values) = translator.tuple(&self.expr);
+            for (value, register) in values.into_iter().zip(r.argument) {
+                stmts.push(Stmt::checked_move(Temp::wrapped((*register).into()), value));
+            }
+            return Stmt::from(&stmts[..]);
+        }
         Stmt::checked_move(
             Temp::wrapped(r.ret.to_string()),
             self.expr.translate(translator),
@@ -570,10 +1249,14 @@ impl Translate<Stmt> for ast::node::Return {
     }
 }
 
-impl Translate<Stmt> for ast::node::VarDecl {
+impl Translate<Stmt> for hir::node::VarDecl {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
-        let name = self.name.to_string();
-        if matches!(self.expr, AstExpr::Init(_)) {
+        if self.ty.elements().is_some() {
+            let (stmts, values) = translator.tuple(&self.expr);
+            return bind(translator, stmts, &self.name, &self.ty, values);
+        }
+        let name = self.name.clone();
+        if matches!(self.expr, HirExpr::Init(_)) {
             translator.ctx.name.push(name.clone());
         }
         let expr = self.expr.translate(translator);
@@ -581,25 +1264,81 @@ impl Translate<Stmt> for ast::node::VarDecl {
         let id = translator.function.unwrap();
         let frame = translator.functions.get_mut(&id).unwrap();
         // No matter what, variables are always F::word_size() (either pointer to first element or the value itself)
-        let target = frame.allocate(
-            &name,
-            !matches!(self.ty.base.lexeme, Some("int" | "float" | "bool")),
-        );
+        let target = frame.allocate(&name, self.ty.is_ptr());
         Stmt::checked_move(target, expr)
     }
 }
 
-impl Translate<Stmt> for ast::node::FuncDecl {
+impl Translate<Stmt> for hir::node::Destructure {
+    fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
+        let (mut stmts, values) = translator.tuple(&self.expr);
+        let Type::Tuple(elements) = self.expr.ty() else {
+            unreachable!("only tuples can be destructured")
+        };
+        let mut values = values.into_iter();
+        for (name, ty) in self.names.iter().zip(&elements) {
+            let values = values.by_ref().take(ty.leaves().len()).collect();
+            stmts.push(bind(translator, vec![], name, ty, values));
+        }
+        Stmt::from(&stmts[..])
+    }
+}
+
+/// Stores `values` in new slots for each element of the variable `name` of type `ty`, after
+/// running `stmts`.
+fn bind<I: ArchInstr, F: Frame<I>>(
+    translator: &mut Translator<I, F>,
+    mut stmts: Vec<Stmt>,
+    name: &str,
+    ty: &Type,
+    values: Vec<Expr>,
+) -> Stmt {
+    let id = translator.function.unwrap();
+    let frame = translator.functions.get_mut(&id).unwrap();
+    for ((path, ty), value) in ty.leaves().into_iter().zip(values) {
+        let target = frame.allocate(&format!("{name}{path}"), ty.is_ptr());
+        stmts.push(Stmt::checked_move(target, value));
+    }
+    Stmt::from(&stmts[..])
+}
+
+/// Runs `stmts` before evaluating to `expr`.
+fn sequence(stmts: &[Stmt], expr: Expr) -> Expr {
+    if stmts.is_empty() {
+        expr
+    } else {
+        ESeq::wrapped(Stmt::from(stmts), expr)
+    }
+}
+
+impl Translate<Stmt> for FuncDecl {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Stmt {
         let frame = F::new(self);
         let r = F::registers();
         translator.functions.insert(self.id, frame);
         translator.function = Some(self.id);
-        let mut stmts: Vec<Stmt> = vec![Label::wrapped(self.name.to_string())]
-            .into_iter()
-            .chain(self.body.iter().map(|stmt| stmt.translate(translator)))
-            .collect();
-        if self.ty.is_none() || self.ty.as_ref().unwrap().base.lexeme == Some("void") {
+        let mut stmts: Vec<Stmt> = vec![Label::wrapped(self.label())];
+        if let Some(captures) = &self.captures {
+            // The captured variables are copied out of the closure into the frame
+            let environment = Temp::next();
+            stmts.push(Stmt::checked_move(
+                Temp::wrapped(environment.clone()),
+                translator.frame().get(hir::node::ENVIRONMENT),
+            ));
+            for (i, capture) in captures.iter().enumerate() {
+                let frame = translator.functions.get_mut(&self.id).unwrap();
+                let target = frame.allocate(&capture.name, capture.ty.is_ptr());
+                let offset = ((i + runtime::CLASS_METADATA_FIELDS) * F::word_size())
+                    .try_into()
+                    .unwrap();
+                stmts.push(Stmt::checked_move(
+                    target,
+                    Mem::wrapped(Temp::wrapped(environment.clone()), offset),
+                ));
+            }
+        }
+        stmts.extend(self.body.iter().map(|stmt| stmt.translate(translator)));
+        if matches!(self.ty, Type::Void) {
             // If the function returns void, explicitly zero out the return register. This can
             // cause unwanted behavior in the garbage collector because if the last call is an
             // allocation: that pointer will be copied to the parent frame and be considered
@@ -613,24 +1352,11 @@ impl Translate<Stmt> for ast::node::FuncDecl {
     }
 }
 
-impl Translate<Vec<Stmt>> for ast::node::ClassDecl {
+impl Translate<Vec<Stmt>> for hir::node::ClassDecl {
     fn translate<I: ArchInstr, F: Frame<I>>(&self, translator: &mut Translator<I, F>) -> Vec<Stmt> {
         self.methods
             .iter()
-            .map(|method| {
-                let mut clone = FuncDecl::new(
-                    method.name.clone(),
-                    method.params.clone(),
-                    method.ty.clone(),
-                    method.tp.clone(),
-                    method.body.clone(),
-                    false,
-                );
-                clone.id = method.id;
-                let lexeme = || -> &'static str { format!("{}.{}", self.name, clone.name).leak() };
-                clone.name = Token::new(Kind::Identifier, Some(lexeme()), Span::default());
-                clone.translate(translator)
-            })
+            .map(|method| method.translate(translator))
             .collect()
     }
 }
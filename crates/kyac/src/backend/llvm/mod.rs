@@ -1,29 +1,30 @@
-mod builtins;
-
 use crate::{
-    ast::{
-        node::{self, ClassDecl, Ident},
-        ty::Type,
-        Decl, Expr, Stmt,
+    builtins,
+    hir::{
+        node::{self, Callee},
+        Decl, Expr, Stmt, Type,
     },
-    backend::llvm::builtins::Builtins,
-    pass::{ResolvedMetaInfo, Symbol, SymbolTable},
-    token::{Kind, Span, Token},
+    pass::{Symbol, SymbolTable},
+    token::Kind,
 };
 use inkwell::{
+    basic_block::BasicBlock,
     builder::Builder,
     context::Context,
     module::Module,
     passes::PassManager,
     types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, StructType},
-    values::{AnyValueEnum, BasicMetadataValueEnum, BasicValueEnum, FunctionValue, PointerValue},
+    values::{
+        AggregateValueEnum, AnyValueEnum, BasicMetadataValueEnum, BasicValueEnum, FunctionValue,
+        PointerValue,
+    },
     AddressSpace, FloatPredicate, IntPredicate,
 };
-use std::{collections::HashMap, rc::Rc};
+use std::{cmp::Ordering, collections::HashMap, rc::Rc};
 
 macro_rules! num_instrs  {
     {$self:ident, $bin:ident, $($kind:ident => $int_instr:ident $float_instr:ident),*} => {
-        match $bin.op.kind {
+        match $bin.op {
             $(
                 Kind::$kind => {
                     let left = $self.expr(&$bin.left)?;
@@ -47,9 +48,9 @@ macro_rules! num_instrs  {
 }
 
 macro_rules! bool_instrs {
-    {$self:ident, $bin:ident, $conversion:ident, $build_fn:ident, $ty:ident, $predicate:ident, $($kind:ident => $member:ident),*} => {
-        if $bin.left.ty() == Type::$ty {
-            match $bin.op.kind {
+    {$self:ident, $bin:ident, $conversion:ident, $build_fn:ident, $applies:expr, $predicate:ident, $($kind:ident => $member:ident),*} => {
+        if $applies {
+            match $bin.op {
                 $(
                     Kind::$kind => {
                         let left = $self.expr(&$bin.left)?.$conversion();
@@ -75,6 +76,8 @@ pub enum IrError {
     UndefinedFunction(String),
     #[error("Malformed {0}")]
     Malformed(&'static str),
+    #[error("{0} is not supported by the LLVM backend; try again without `--llvm`")]
+    Unsupported(String),
 }
 
 // TODO: look at having this be less of a mess
@@ -84,18 +87,25 @@ pub struct Ir<'a, 'ctx> {
     builder: &'a Builder<'ctx>,
     fpm: &'a PassManager<FunctionValue<'ctx>>,
     variables: HashMap<String, (PointerValue<'ctx>, Type)>,
-    classes: HashMap<String, (StructType<'ctx>, Rc<ClassDecl>)>,
+    classes: HashMap<String, StructType<'ctx>>,
     function: Option<FunctionValue<'ctx>>,
-    symbols: SymbolTable,
-    meta: ResolvedMetaInfo,
+    symbols: &'a SymbolTable,
 }
 
 impl<'a, 'ctx> Ir<'a, 'ctx> {
-    pub fn build(
-        program: &mut Vec<Decl>,
-        symbols: SymbolTable,
-        meta: ResolvedMetaInfo,
-    ) -> Result<String, IrError> {
+    pub fn build(program: &[Decl], symbols: &SymbolTable) -> Result<String, IrError> {
+        // Classes are lowered to plain structs, which have no dispatch table to find the
+        // implementation of an interface method in
+        if let Some(interface) = symbols
+            .values()
+            .filter_map(Symbol::interface)
+            .min_by_key(|interface| interface.name.to_string())
+        {
+            return Err(IrError::Unsupported(format!(
+                "interface `{}`",
+                interface.name
+            )));
+        }
         let context = Context::create();
         let module = context.create_module("main");
         let builder = context.create_builder();
@@ -119,13 +129,12 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
             function: None,
 
             symbols,
-            meta,
         };
 
-        // Inject builtin function declarations
-        Builtins::inject(&mut ir)?;
+        // Declare the layout of every class up front, since classes may refer to each other
+        ir.classes(program);
 
-        // entrypoint - compile all toplevel nodes
+        // entrypoint - compile all toplevel nodes (including builtin declarations)
         for node in program {
             ir.decl(node)?;
         }
@@ -133,11 +142,15 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
         Ok(ir.module.print_to_string().to_string())
     }
 
-    fn decl(&mut self, decl: &mut Decl) -> Result<AnyValueEnum<'ctx>, IrError> {
+    fn decl(&mut self, decl: &Decl) -> Result<(), IrError> {
         match decl {
-            Decl::Function(fun) => self.function(fun).map(Into::into),
-            Decl::Constant(_) => todo!(),
-            Decl::Class(cls) => Ok(self.class(cls).into()),
+            Decl::Function(fun) => self.function(fun).map(|_| ()),
+            Decl::Class(cls) => {
+                for method in &cls.methods {
+                    self.function(method)?;
+                }
+                Ok(())
+            }
         }
     }
 
@@ -147,61 +160,84 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
             Stmt::Expr(expr) => self.expr(expr),
             Stmt::Return(r) => self.ret(r),
             Stmt::Var(var) => self.var(var),
-            Stmt::If(_) => todo!(),
-            Stmt::While(_) => todo!(),
-            Stmt::For(_) => todo!(),
+            Stmt::Destructure(destructure) => self.destructure(destructure),
+            Stmt::If(i) => self.r#if(i),
+            Stmt::While(w) => self.r#while(w),
+            Stmt::For(f) => self.r#for(f),
         }
     }
 
     fn expr(&mut self, expr: &Expr) -> Result<AnyValueEnum<'ctx>, IrError> {
         match expr {
-            Expr::Str(s) => Ok(self.str(s.value)),
-            Expr::Access(a) => Ok(self.access(a).into()),
+            Expr::Str(s) => Ok(self.str(s.contents())),
+            Expr::Access(a) => self.access(a).map(Into::into),
             Expr::Bool(b) => Ok(self
                 .context
                 .bool_type()
                 .const_int(u64::from(b.value), false)
                 .into()),
             Expr::Float(f) => Ok(self.context.f64_type().const_float(f.value).into()),
+            Expr::Char(c) => Ok(self
+                .context
+                .i32_type()
+                .const_int(u32::from(c.value).into(), false)
+                .into()),
             Expr::Call(call) => self.call(call).map(Into::into),
             Expr::Ident(ident) => self.ident(ident).map(Into::into),
             Expr::Int(n) => Ok(self.int(n.value).into()),
+            // Nothing is ever collected, so the GC never needs to know where the pointers are
+            Expr::Layout(_) => Ok(self.int(0).into()),
             Expr::Binary(binary) => self.binary(binary).map(Into::into),
             Expr::Unary(unary) => self.unary(unary).map(Into::into),
             Expr::Init(init) => self.init(init).map(Into::into),
-            Expr::Range(_) => todo!(),
+            Expr::Closure(closure) => self.closure(closure).map(Into::into),
+            Expr::Convert(convert) => self.convert(convert).map(Into::into),
+            Expr::Nil(_) => Ok(self
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::default())
+                .const_null()
+                .into()),
+            Expr::Tuple(tuple) => self.tuple(tuple).map(Into::into),
+            Expr::Element(element) => self.element(element).map(Into::into),
+            Expr::Wrap(wrap) => self.wrap(wrap).map(Into::into),
+            Expr::Try(t) => self.r#try(t).map(Into::into),
+            // Classes are lowered to plain structs, which carry no class ID to test
+            Expr::Is(is) => Err(IrError::Unsupported(format!(
+                "testing whether an object is a `{}`",
+                is.class
+            ))),
+            Expr::Cast(cast) => Err(IrError::Unsupported(format!(
+                "casting an object to `{}`",
+                cast.ty
+            ))),
         }
     }
 
-    fn build_struct(&mut self, cls: &node::ClassDecl) -> StructType<'ctx> {
-        self.context.struct_type(
-            cls.fields
+    fn classes(&mut self, program: &[Decl]) {
+        let classes: Vec<_> = program
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::Class(cls) => Some(cls),
+                Decl::Function(_) => None,
+            })
+            .collect();
+        for cls in &classes {
+            let ty = self.context.opaque_struct_type(&cls.name);
+            self.classes.insert(cls.name.clone(), ty);
+        }
+        for cls in classes {
+            let fields: Vec<BasicTypeEnum> = cls
+                .layout
                 .iter()
-                .map(|f| match Type::from(&f.ty) {
-                    Type::UserDefined(name) => {
-                        let symbol = self.symbols.get(&name.to_string()).unwrap().clone();
-                        match symbol {
-                            Symbol::Class(cls) => self.build_struct(&cls).into(),
-                            _ => unreachable!(),
-                        }
-                    }
-                    ty => ty.to_basic_type_enum(self),
-                })
-                .collect::<Vec<BasicTypeEnum>>()
-                .as_slice(),
-            false,
-        )
-    }
-
-    fn class(&mut self, cls: &Rc<node::ClassDecl>) -> BasicValueEnum<'ctx> {
-        let class = self.build_struct(cls);
-        self.classes
-            .insert(cls.name.to_string(), (class, Rc::clone(cls)));
-        self.context.i64_type().const_int(0, false).into()
+                .map(|ty| ty.to_basic_type_enum(self))
+                .collect();
+            self.classes[&cls.name].set_body(fields.as_slice(), false);
+        }
     }
 
     fn init(&mut self, init: &node::Init) -> Result<BasicValueEnum<'ctx>, IrError> {
-        let (cls, _) = *self.classes.get(&init.name.to_string()).unwrap();
+        let cls = init.ty.to_basic_type_enum(self).into_struct_type();
         let mut values = vec![];
         for init in &init.initializers {
             values.push(
@@ -210,59 +246,92 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
                     .map_err(|()| IrError::Malformed("init expression"))?,
             );
         }
-        Ok(cls.const_named_struct(values.as_slice()).into())
+        let object: BasicValueEnum = cls.const_named_struct(values.as_slice()).into();
+        if let Some(label) = &init.method {
+            let function = self
+                .module
+                .get_function(label)
+                .ok_or_else(|| IrError::UndefinedFunction(label.clone()))?;
+            self.builder.build_call(function, &[object.into()], "init");
+        }
+        Ok(object)
     }
 
-    fn indices(&mut self, access: &node::Access) -> (Vec<(u32, Type)>, Type) {
-        let access = self.meta.access.remove(&access.id).unwrap();
-        let indices = access
-            .symbols
-            .iter()
-            .zip(access.indices)
-            .map(|(symbol, index)| (u32::try_from(index).unwrap(), symbol.ty()))
+    /// The layout of a closure: the function it calls, followed by the variables it captured
+    fn closure_type(&self, captures: impl Iterator<Item = Type>) -> StructType<'ctx> {
+        let ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+        let fields: Vec<BasicTypeEnum> = std::iter::once(ptr.into())
+            .chain(captures.map(|ty| ty.to_basic_type_enum(self)))
             .collect();
-        (indices, access.ty)
+        self.context.struct_type(fields.as_slice(), false)
     }
 
-    fn gep(&mut self, access: &node::Access) -> (PointerValue<'ctx>, BasicTypeEnum<'ctx>) {
-        let (ptr, ty) = match access.chain.first().unwrap() {
-            Expr::Ident(ident) => self.variables.get(&ident.name.to_string()).unwrap().clone(),
+    fn closure(&mut self, closure: &node::Closure) -> Result<PointerValue<'ctx>, IrError> {
+        let ty = self.closure_type(closure.captures.iter().map(Expr::ty));
+        // There is no garbage collector to trace the closure, so it is never freed
+        let ptr = self
+            .builder
+            .build_malloc(ty, "closure")
+            .map_err(|_| IrError::Malformed("closure"))?;
+        let function = self
+            .module
+            .get_function(&closure.function)
+            .ok_or_else(|| IrError::UndefinedFunction(closure.function.clone()))?;
+        let fields = std::iter::once(Ok(function.as_global_value().as_pointer_value().into()))
+            .chain(closure.captures.iter().map(|capture| {
+                self.expr(capture)?
+                    .try_into()
+                    .map_err(|()| IrError::Malformed("captured variable"))
+            }))
+            .collect::<Result<Vec<BasicValueEnum>, _>>()?;
+        for (i, value) in fields.into_iter().enumerate() {
+            let field = self
+                .builder
+                .build_struct_gep(ty, ptr, u32::try_from(i).unwrap(), "tmp")
+                .unwrap();
+            self.builder.build_store(field, value);
+        }
+        Ok(ptr)
+    }
+
+    fn gep(&mut self, access: &node::Access) -> Result<PointerValue<'ctx>, IrError> {
+        let ptr = match &access.object {
+            Expr::Ident(ident) => match self.variables.get(&ident.name) {
+                Some((ptr, _)) => *ptr,
+                None => return Err(IrError::Undefined(ident.name.clone())),
+            },
+            Expr::Access(object) => self.gep(object)?,
             Expr::Call(_) => todo!(),
             _ => unimplemented!(),
         };
-        let (indices, last) = self.indices(access);
-        let ty = self
-            .classes
-            .get(&ty.to_string())
-            .map(|(ty, _)| ty)
-            .copied()
-            .unwrap();
-        let gep = indices.iter().skip(1).fold(
-            self.builder
-                .build_struct_gep(ty, ptr, indices[0].0, "tmp")
-                .unwrap(),
-            |gep, (index, ty)| {
-                self.builder
-                    .build_struct_gep(ty.to_basic_type_enum(self), gep, *index, "tmp")
-                    .unwrap()
-            },
-        );
-        (gep, last.to_basic_type_enum(self))
+        let ty = access.object.ty().to_basic_type_enum(self);
+        let index = u32::try_from(access.field).unwrap();
+        Ok(self
+            .builder
+            .build_struct_gep(ty, ptr, index, "tmp")
+            .unwrap())
     }
 
-    fn access(&mut self, access: &node::Access) -> BasicValueEnum<'ctx> {
-        let (gep, field_ty) = self.gep(access);
-        self.builder.build_load(field_ty, gep, "tmp")
+    fn access(&mut self, access: &node::Access) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let gep = self.gep(access)?;
+        Ok(self
+            .builder
+            .build_load(access.ty.to_basic_type_enum(self), gep, "tmp"))
     }
 
     /// Compiles a function prototype into a `FunctionValue`
-    fn prototype(&mut self, func: &node::FuncDecl) -> FunctionValue<'ctx> {
+    fn prototype(&mut self, func: &node::FuncDecl, name: &str) -> FunctionValue<'ctx> {
         // Collect the function argument types and convert them to LLVM types
-        let args: Vec<BasicMetadataTypeEnum> = func
+        let mut args: Vec<BasicMetadataTypeEnum> = func
             .params
             .iter()
             .map(|p| p.ty.to_basic_type_enum(self).into())
             .collect();
+        // Builtins that allocate also take the frame for the garbage collector to scan
+        if func.external && builtins::ALLOCATING.contains(&name) {
+            let ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+            args.append(&mut vec![ptr.into(), self.context.i64_type().into()]);
+        }
         let types = args.as_slice();
 
         // `fn_type` creates a function type with the specified `types` (argument types)
@@ -273,18 +342,16 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
         };
 
         // Adds the function to the module as a complete function value
-        let val = self
-            .module
-            .add_function(&func.name.to_string(), fn_ty, None);
+        let val = self.module.add_function(name, fn_ty, None);
 
         // Iterate through the function arguments and assign names to them
-        for (i, arg) in val.get_param_iter().enumerate() {
-            let name = func.params[i].name.to_string();
+        for (arg, param) in val.get_param_iter().zip(&func.params) {
+            let name = &param.name;
             match arg {
-                BasicValueEnum::IntValue(_) => arg.into_int_value().set_name(&name),
-                BasicValueEnum::FloatValue(_) => arg.into_float_value().set_name(&name),
-                BasicValueEnum::PointerValue(_) => arg.into_pointer_value().set_name(&name),
-                BasicValueEnum::StructValue(_) => arg.into_struct_value().set_name(&name),
+                BasicValueEnum::IntValue(_) => arg.into_int_value().set_name(name),
+                BasicValueEnum::FloatValue(_) => arg.into_float_value().set_name(name),
+                BasicValueEnum::PointerValue(_) => arg.into_pointer_value().set_name(name),
+                BasicValueEnum::StructValue(_) => arg.into_struct_value().set_name(name),
                 _ => unimplemented!("formal parameter type `{arg}` is not implemented"),
             };
         }
@@ -293,18 +360,9 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
     }
 
     /// Wraps the main function
-    pub fn main(&mut self, func: &Rc<node::FuncDecl>) -> Result<FunctionValue<'ctx>, IrError> {
+    pub fn main(&mut self, func: &node::FuncDecl) -> Result<FunctionValue<'ctx>, IrError> {
         // Rename the main function to avoid conflicts with the wrapper
-        let mut modified = node::FuncDecl::new(
-            func.name.clone(),
-            func.params.clone(),
-            func.ty.clone(),
-            func.tp.clone(),
-            func.body.clone(),
-            func.external,
-        );
-        modified.name.lexeme = Some("_main");
-        self.function(&Rc::new(modified))?;
+        let inner = self.compile(func, "_main")?;
 
         // TODO: collect CLI arguments
         let types: &[BasicMetadataTypeEnum] = &[];
@@ -315,7 +373,7 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
         let entry = self.context.append_basic_block(val, "entry");
         self.builder.position_at_end(entry);
 
-        self.call(&main())?;
+        self.builder.build_call(inner, &[], "tmp");
         // TODO: handle non-zero exit codes
         self.builder
             .build_return(Some(&self.context.i64_type().const_int(0, false)));
@@ -323,14 +381,22 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
         Ok(val)
     }
 
-    /// Compiles a function body into a `FunctionValue`
-    pub fn function(&mut self, func: &Rc<node::FuncDecl>) -> Result<FunctionValue<'ctx>, IrError> {
+    pub fn function(&mut self, func: &node::FuncDecl) -> Result<FunctionValue<'ctx>, IrError> {
         // Special case main function
-        if func.name == "main" {
+        if func.class.is_none() && func.name == "main" {
             return self.main(func);
         }
+        self.compile(func, &func.label())
+    }
+
+    /// Compiles a function body into a `FunctionValue`
+    fn compile(
+        &mut self,
+        func: &node::FuncDecl,
+        name: &str,
+    ) -> Result<FunctionValue<'ctx>, IrError> {
         // Compile our function prototype and set as current function
-        let proto = self.prototype(func);
+        let proto = self.prototype(func, name);
         if func.external {
             return Ok(proto);
         }
@@ -345,19 +411,45 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
 
         // Iterate through the function prototype, create allocations for each argument and add it to variables map
         for (i, arg) in proto.get_param_iter().enumerate() {
-            let name = func.params[i].name.to_string();
-            let allocation = self.alloca(&name, &arg);
+            let param = &func.params[i];
+            let allocation = self.alloca(&param.name, &arg);
             self.builder.build_store(allocation, arg);
             self.variables
-                .insert(name, (allocation, (&func.params[i].ty).into()));
+                .insert(param.name.clone(), (allocation, param.ty.clone()));
+        }
+
+        // The body of a closure starts by copying the variables it captured out of the closure
+        if let Some(captures) = &func.captures {
+            let ty = self.closure_type(captures.iter().map(|capture| capture.ty.clone()));
+            let environment = proto.get_first_param().unwrap().into_pointer_value();
+            for (i, capture) in captures.iter().enumerate() {
+                let field = self
+                    .builder
+                    .build_struct_gep(ty, environment, u32::try_from(i + 1).unwrap(), "tmp")
+                    .unwrap();
+                let value = self.builder.build_load(
+                    capture.ty.to_basic_type_enum(self),
+                    field,
+                    &capture.name,
+                );
+                let allocation = self.alloca(&capture.name, &value);
+                self.builder.build_store(allocation, value);
+                self.variables
+                    .insert(capture.name.clone(), (allocation, capture.ty.clone()));
+            }
         }
 
         // Compile the body of the function
         self.block(&func.body)?;
 
-        // Add a return statement on behalf of the user if the function returns void
-        if func.ty == Type::Void {
-            self.builder.build_return(None);
+        // Add a return statement on behalf of the user if the function returns void. Otherwise,
+        // a block left open can't be reached, like the end of an `if` whose branches both return
+        if !self.terminated() {
+            if func.ty == Type::Void {
+                self.builder.build_return(None);
+            } else {
+                self.builder.build_unreachable();
+            }
         }
 
         // Once we've compiled the function, we can discard the variables map
@@ -392,8 +484,7 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
 
     /// Injects a string literal
     fn str(&mut self, s: &str) -> AnyValueEnum<'ctx> {
-        // Figure out the actual bytes of the string excluding the opening and closing quotes
-        let bytes = s[1..s.len() - 1].as_bytes().to_vec();
+        let bytes = s.as_bytes().to_vec();
         // Create a global string pointer with the appropriate length of zeroed out bytes
         let global = self
             .builder
@@ -407,7 +498,7 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
     fn int(&self, n: i64) -> BasicValueEnum<'ctx> {
         self.context
             .i64_type()
-            .const_int(n.try_into().unwrap(), false)
+            .const_int(u64::from_ne_bytes(n.to_ne_bytes()), true)
             .into()
     }
 
@@ -421,29 +512,224 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
     }
 
     fn var(&mut self, var: &node::VarDecl) -> Result<AnyValueEnum<'ctx>, IrError> {
-        let ty = Type::from(&var.ty);
-        let name = var.name.to_string();
+        let name = var.name.clone();
         let value = self
             .expr(&var.expr)?
             .try_into()
             .map_err(|()| IrError::Malformed("variable declaration"))?;
         let alloca = self.alloca(&name, &value);
         self.builder.build_store(alloca, value);
-        self.variables.insert(name, (alloca, ty));
+        self.variables.insert(name, (alloca, var.ty.clone()));
         Ok(value.into())
     }
 
+    fn destructure(
+        &mut self,
+        destructure: &node::Destructure,
+    ) -> Result<AnyValueEnum<'ctx>, IrError> {
+        let tuple = self.expr(&destructure.expr)?.into_struct_value();
+        let Type::Tuple(elements) = destructure.expr.ty() else {
+            return Err(IrError::Malformed("destructuring of a non-tuple"));
+        };
+        for (i, (name, ty)) in destructure.names.iter().zip(elements).enumerate() {
+            let value = self
+                .builder
+                .build_extract_value(tuple, u32::try_from(i).unwrap(), name)
+                .unwrap();
+            let alloca = self.alloca(name, &value);
+            self.builder.build_store(alloca, value);
+            self.variables.insert(name.clone(), (alloca, ty));
+        }
+        Ok(tuple.into())
+    }
+
+    /// Builds a tuple as a struct value, which is returned from functions like any other value.
+    fn tuple(&mut self, tuple: &node::Tuple) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let ty = tuple.ty.to_basic_type_enum(self).into_struct_type();
+        let mut value: AggregateValueEnum = ty.get_undef().into();
+        for (i, element) in tuple.elements.iter().enumerate() {
+            let element: BasicValueEnum = self
+                .expr(element)?
+                .try_into()
+                .map_err(|()| IrError::Malformed("tuple element"))?;
+            value = self
+                .builder
+                .build_insert_value(value, element, u32::try_from(i).unwrap(), "tmp")
+                .unwrap();
+        }
+        Ok(value.into_struct_value().into())
+    }
+
+    fn element(&mut self, element: &node::Element) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let tuple = self.expr(&element.tuple)?.into_struct_value();
+        Ok(self
+            .builder
+            .build_extract_value(tuple, u32::try_from(element.index).unwrap(), "tmp")
+            .unwrap())
+    }
+
+    /// Builds a result as a struct of whether it holds a value, the value and the error. The half
+    /// it does not hold is zeroed.
+    fn wrap(&mut self, wrap: &node::Wrap) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let ty = wrap.ty.to_basic_type_enum(self).into_struct_type();
+        let held: BasicValueEnum = self
+            .expr(&wrap.value)?
+            .try_into()
+            .map_err(|()| IrError::Malformed("value of a result"))?;
+        let ok = self
+            .context
+            .bool_type()
+            .const_int(u64::from(wrap.ok), false);
+        let result = self
+            .builder
+            .build_insert_value(ty.const_zero(), ok, 0, "tmp")
+            .unwrap();
+        let result = self
+            .builder
+            .build_insert_value(result, held, if wrap.ok { 1 } else { 2 }, "tmp")
+            .unwrap();
+        Ok(result.into_struct_value().into())
+    }
+
+    /// Reads the value of a result, first returning a result holding its error from the function
+    /// if it has one.
+    fn r#try(&mut self, t: &node::Try) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let result = self.expr(&t.expr)?.into_struct_value();
+        let ok = self
+            .builder
+            .build_extract_value(result, 0, "ok")
+            .unwrap()
+            .into_int_value();
+        let function = self.function.unwrap();
+        let fail = self.context.append_basic_block(function, "fail");
+        let succeed = self.context.append_basic_block(function, "succeed");
+        self.builder.build_conditional_branch(ok, succeed, fail);
+
+        self.builder.position_at_end(fail);
+        let error = self
+            .builder
+            .build_extract_value(result, 2, "error")
+            .unwrap();
+        let ty = t.ret.to_basic_type_enum(self).into_struct_type();
+        let ret = self
+            .builder
+            .build_insert_value(ty.const_zero(), error, 2, "tmp")
+            .unwrap();
+        self.builder.build_return(Some(&ret.into_struct_value()));
+
+        self.builder.position_at_end(succeed);
+        Ok(self
+            .builder
+            .build_extract_value(result, 1, "value")
+            .unwrap())
+    }
+
+    fn r#for(&mut self, f: &node::For) -> Result<AnyValueEnum<'ctx>, IrError> {
+        let lowered = f.lowered();
+        self.block(&lowered.setup)?;
+        let function = self.function.unwrap();
+        let test = self.context.append_basic_block(function, "test");
+        let body = self.context.append_basic_block(function, "body");
+        let done = self.context.append_basic_block(function, "done");
+        self.builder.build_unconditional_branch(test);
+
+        self.builder.position_at_end(test);
+        let condition = self.expr(&lowered.condition)?.into_int_value();
+        self.builder.build_conditional_branch(condition, body, done);
+
+        self.builder.position_at_end(body);
+        self.block(&lowered.enter)?;
+        self.block(&f.body)?;
+        // A body that ends by returning never reaches the next iteration
+        if !self.terminated() {
+            self.block(&lowered.advance)?;
+            self.builder.build_unconditional_branch(test);
+        }
+
+        self.builder.position_at_end(done);
+        Ok(self.context.i64_type().const_zero().into())
+    }
+
+    fn r#if(&mut self, i: &node::If) -> Result<AnyValueEnum<'ctx>, IrError> {
+        let condition = self.expr(&i.condition)?.into_int_value();
+        let function = self.function.unwrap();
+        let is = self.context.append_basic_block(function, "is");
+        let otherwise = self.context.append_basic_block(function, "otherwise");
+        let done = self.context.append_basic_block(function, "done");
+        self.builder
+            .build_conditional_branch(condition, is, otherwise);
+
+        // A branch that ends by returning never reaches the code after the `if`
+        for (block, body) in [(is, &i.is), (otherwise, &i.otherwise)] {
+            self.builder.position_at_end(block);
+            self.block(body)?;
+            if !self.terminated() {
+                self.builder.build_unconditional_branch(done);
+            }
+        }
+
+        self.builder.position_at_end(done);
+        Ok(self.context.i64_type().const_zero().into())
+    }
+
+    fn r#while(&mut self, w: &node::While) -> Result<AnyValueEnum<'ctx>, IrError> {
+        let function = self.function.unwrap();
+        let test = self.context.append_basic_block(function, "test");
+        let body = self.context.append_basic_block(function, "body");
+        let done = self.context.append_basic_block(function, "done");
+        self.builder.build_unconditional_branch(test);
+
+        self.builder.position_at_end(test);
+        let condition = self.expr(&w.condition)?.into_int_value();
+        self.builder.build_conditional_branch(condition, body, done);
+
+        self.builder.position_at_end(body);
+        self.block(&w.body)?;
+        if !self.terminated() {
+            self.builder.build_unconditional_branch(test);
+        }
+
+        self.builder.position_at_end(done);
+        Ok(self.context.i64_type().const_zero().into())
+    }
+
+    /// Whether the block being built already ends, such as by returning.
+    fn terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .and_then(BasicBlock::get_terminator)
+            .is_some()
+    }
+
+    /// The pointer to an element of a tuple held in a variable, or to a field of a struct held in
+    /// a variable or embedded in an object.
+    fn element_gep(&mut self, element: &node::Element) -> Result<PointerValue<'ctx>, IrError> {
+        let ptr = match &element.tuple {
+            Expr::Ident(ident) => match self.variables.get(&ident.name) {
+                Some((ptr, _)) => *ptr,
+                None => return Err(IrError::Undefined(ident.name.clone())),
+            },
+            Expr::Element(tuple) => self.element_gep(tuple)?,
+            Expr::Access(access) => self.gep(access)?,
+            _ => return Err(IrError::Malformed("element of a temporary tuple")),
+        };
+        let ty = element.tuple.ty().to_basic_type_enum(self);
+        let index = u32::try_from(element.index).unwrap();
+        Ok(self
+            .builder
+            .build_struct_gep(ty, ptr, index, "tmp")
+            .unwrap())
+    }
+
     fn assign(&mut self, assign: &node::Assign) -> Result<BasicValueEnum<'ctx>, IrError> {
         // Retreive the pointer to the variable in question
         let ptr = match &assign.target {
-            Expr::Ident(ref ident) => {
-                let name = ident.name.to_string();
-                match self.variables.get(&name) {
-                    Some((ptr, _)) => *ptr,
-                    None => return Err(IrError::Undefined(name)),
-                }
-            }
-            Expr::Access(access) => self.gep(access).0,
+            Expr::Ident(ident) => match self.variables.get(&ident.name) {
+                Some((ptr, _)) => *ptr,
+                None => return Err(IrError::Undefined(ident.name.clone())),
+            },
+            Expr::Access(access) => self.gep(access)?,
+            Expr::Element(element) => self.element_gep(element)?,
             _ => unimplemented!(),
         };
         // Compile the right-hand-side of assignment to an expression
@@ -457,6 +743,32 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
     }
 
     fn binary(&mut self, binary: &node::Binary) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let ty = binary.left.ty();
+        if binary.op == Kind::Slash && ty.is_unsigned() {
+            let left = self.expr(&binary.left)?.into_int_value();
+            let right = self.expr(&binary.right)?.into_int_value();
+            return Ok(self
+                .builder
+                .build_int_unsigned_div(left, right, "tmp")
+                .into());
+        }
+
+        // An optional value is compared with `nil` by testing whether it is a null pointer
+        if matches!(ty, Type::Optional(_) | Type::Nil) {
+            let value = match ty {
+                Type::Nil => &binary.right,
+                _ => &binary.left,
+            };
+            let AnyValueEnum::PointerValue(ptr) = self.expr(value)? else {
+                return Err(IrError::Malformed("optional value"));
+            };
+            return Ok(if binary.op == Kind::EqualEqual {
+                self.builder.build_is_null(ptr, "tmp").into()
+            } else {
+                self.builder.build_is_not_null(ptr, "tmp").into()
+            });
+        }
+
         num_instrs! { self, binary,
             Plus => build_int_add build_float_add,
             Minus => build_int_sub build_float_sub,
@@ -464,7 +776,7 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
             Slash => build_int_signed_div build_float_div
         }
 
-        bool_instrs! { self, binary, into_int_value, build_int_compare, Int, IntPredicate,
+        bool_instrs! { self, binary, into_int_value, build_int_compare, ty.is_integer() && !ty.is_unsigned(), IntPredicate,
             EqualEqual => EQ,
             BangEqual => NE,
             GreaterEqual => SGE,
@@ -473,7 +785,16 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
             Less => SLT
         }
 
-        bool_instrs! { self, binary, into_float_value, build_float_compare, Float, FloatPredicate,
+        bool_instrs! { self, binary, into_int_value, build_int_compare, ty.is_unsigned() || ty == Type::Char, IntPredicate,
+            EqualEqual => EQ,
+            BangEqual => NE,
+            GreaterEqual => UGE,
+            LessEqual => ULE,
+            Greater => UGT,
+            Less => ULT
+        }
+
+        bool_instrs! { self, binary, into_float_value, build_float_compare, ty.is_float(), FloatPredicate,
             EqualEqual => OEQ,
             BangEqual => ONE,
             GreaterEqual => OGE,
@@ -483,12 +804,57 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
         }
 
         // finally fail if still not implemented (should be type error)
-        unimplemented!("binary operation not implemented for {:?}", binary.op.kind)
+        unimplemented!("binary operation not implemented for {:?}", binary.op)
+    }
+
+    /// Converts a number to another numeric type, extending or truncating it to the new width.
+    fn convert(&mut self, convert: &node::Convert) -> Result<BasicValueEnum<'ctx>, IrError> {
+        let from = convert.expr.ty();
+        let to = &convert.ty;
+        let value = self.expr(&convert.expr)?;
+        let ty = to.to_basic_type_enum(self);
+        Ok(match (from.is_float(), to.is_float()) {
+            (false, false) => {
+                let (value, ty) = (value.into_int_value(), ty.into_int_type());
+                match from.bits().cmp(&to.bits()) {
+                    // A character is an unsigned scalar value
+                    Ordering::Less if from.is_unsigned() || from == Type::Char => {
+                        self.builder.build_int_z_extend(value, ty, "tmp")
+                    }
+                    Ordering::Less => self.builder.build_int_s_extend(value, ty, "tmp"),
+                    Ordering::Greater => self.builder.build_int_truncate(value, ty, "tmp"),
+                    Ordering::Equal => value,
+                }
+                .into()
+            }
+            (false, true) => {
+                let (value, ty) = (value.into_int_value(), ty.into_float_type());
+                if from.is_unsigned() {
+                    self.builder.build_unsigned_int_to_float(value, ty, "tmp")
+                } else {
+                    self.builder.build_signed_int_to_float(value, ty, "tmp")
+                }
+                .into()
+            }
+            (true, false) => {
+                let (value, ty) = (value.into_float_value(), ty.into_int_type());
+                if to.is_unsigned() {
+                    self.builder.build_float_to_unsigned_int(value, ty, "tmp")
+                } else {
+                    self.builder.build_float_to_signed_int(value, ty, "tmp")
+                }
+                .into()
+            }
+            (true, true) => self
+                .builder
+                .build_float_cast(value.into_float_value(), ty.into_float_type(), "tmp")
+                .into(),
+        })
     }
 
     fn unary(&mut self, unary: &node::Unary) -> Result<BasicValueEnum<'ctx>, IrError> {
         let expr = self.expr(&unary.expr)?;
-        Ok(match unary.op.kind {
+        Ok(match unary.op {
             Kind::Minus => match expr {
                 AnyValueEnum::IntValue(i) => i.const_neg().into(),
                 AnyValueEnum::FloatValue(f) => f.const_neg().into(),
@@ -498,13 +864,18 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
                 AnyValueEnum::IntValue(i) => i.const_not().into(),
                 _ => unimplemented!("cannot perform `!` on {expr:?}"),
             },
-            _ => unimplemented!("unary operation not implemented for {:?}", unary.op.kind),
+            _ => unimplemented!("unary operation not implemented for {:?}", unary.op),
         })
     }
 
     fn call(&mut self, call: &node::Call) -> Result<BasicValueEnum<'ctx>, IrError> {
+        // A function value is evaluated before its arguments, and passed as the first of them
+        let closure = match &call.callee {
+            Callee::Closure(closure) => Some(self.expr(closure)?.into_pointer_value()),
+            _ => None,
+        };
         // Collect the arguments and convert them to LLVM types
-        let mut args: Vec<BasicMetadataValueEnum> = Vec::with_capacity(call.args.len());
+        let mut args: Vec<BasicMetadataValueEnum> = closure.iter().map(|&c| c.into()).collect();
         for arg in &call.args {
             args.push(
                 self.expr(arg)?
@@ -512,14 +883,26 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
                     .map_err(|()| IrError::Malformed("expression to call expr"))?,
             );
         }
-        // Retreive the name of the function
-        // (this needs to be updated when member access is implemented)
-        let name = match *call.left {
-            Expr::Ident(ref ident) => ident.name.to_string(),
-            Expr::Access(_) => todo!(),
-            _ => unimplemented!(),
+        // Retreive the name of the function, which for a method is the label of its most
+        // derived implementation
+        let name = match &call.callee {
+            Callee::Function(name) | Callee::Static(name) => name.clone(),
+            Callee::Method { class, name } => Symbol::Class(Rc::clone(class))
+                .methods(self.symbols)
+                .into_iter()
+                .map(|(label, _)| label)
+                .find(|label| label.rsplit_once('.').is_some_and(|(_, n)| n == name))
+                .ok_or_else(|| IrError::UndefinedFunction(name.clone()))?,
+            Callee::Interface { .. } => unreachable!("interfaces are rejected before building"),
+            Callee::Closure(_) => return Ok(self.indirect(call, closure.unwrap(), &args)),
         };
-        match self.get_function(&name, &args) {
+        if builtins::ALLOCATING.contains(&name.as_str()) {
+            // There is no stack map for LLVM-compiled code, so a null frame tells the runtime
+            // not to collect
+            let ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+            args.append(&mut vec![ptr.const_null().into(), self.int(0).into()]);
+        }
+        match self.module.get_function(&name) {
             Some(func) => Ok(self
                 .builder
                 .build_call(func, args.as_slice(), "tmp")
@@ -530,58 +913,52 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
                     || self.context.i64_type().const_int(0, false).into(),
                     std::convert::identity,
                 )),
-            None => Err(IrError::UndefinedFunction(name.to_string())),
+            None => Err(IrError::UndefinedFunction(name)),
         }
     }
 
-    fn load(
-        &self,
-        var: &(PointerValue<'ctx>, Type),
-        ty: BasicTypeEnum<'ctx>,
-        ident: &node::Ident,
+    /// Calls the function held by `closure`, which is the first of `args`
+    fn indirect(
+        &mut self,
+        call: &node::Call,
+        closure: PointerValue<'ctx>,
+        args: &[BasicMetadataValueEnum<'ctx>],
     ) -> BasicValueEnum<'ctx> {
-        self.builder.build_load(ty, var.0, &ident.name.to_string())
+        let ptr = self.context.i8_type().ptr_type(AddressSpace::default());
+        let params: Vec<BasicMetadataTypeEnum> = std::iter::once(ptr.into())
+            .chain(
+                call.args
+                    .iter()
+                    .map(|arg| arg.ty().to_basic_type_enum(self).into()),
+            )
+            .collect();
+        let fn_ty = if call.ty == Type::Void {
+            self.context.void_type().fn_type(params.as_slice(), false)
+        } else {
+            call.ty
+                .to_basic_type_enum(self)
+                .fn_type(params.as_slice(), false)
+        };
+        // The function is the first field of the closure
+        let function = self
+            .builder
+            .build_load(ptr, closure, "function")
+            .into_pointer_value();
+        self.builder
+            .build_indirect_call(fn_ty, function, args, "tmp")
+            .try_as_basic_value()
+            .left()
+            .unwrap_or_else(|| self.context.i64_type().const_int(0, false).into())
     }
 
     fn ident(&mut self, ident: &node::Ident) -> Result<BasicValueEnum<'ctx>, IrError> {
-        let name = ident.name.to_string();
-        match self.variables.get(&name) {
-            Some(var) => Ok(self.load(var, var.1.to_basic_type_enum(self), ident)),
-            None => Err(IrError::Undefined(name)),
-        }
-    }
-
-    #[inline]
-    fn get_function(
-        &self,
-        name: &str,
-        args: &[BasicMetadataValueEnum<'ctx>],
-    ) -> Option<FunctionValue<'ctx>> {
-        match name {
-            // HACK: handle builtin functions
-            "println" => match args.first().unwrap() {
-                BasicMetadataValueEnum::PointerValue(_) => self.module.get_function("println_str"),
-                BasicMetadataValueEnum::IntValue(i) => {
-                    if i.get_type().get_bit_width() == 1 {
-                        self.module.get_function("println_bool")
-                    } else {
-                        self.module.get_function("println_int")
-                    }
-                }
-                BasicMetadataValueEnum::FloatValue(_) => self.module.get_function("println_float"),
-                node => unreachable!("impossible to call println with {node:?}"),
-            },
-            "max" => match args.first().unwrap() {
-                BasicMetadataValueEnum::IntValue(_) => self.module.get_function("max_int"),
-                BasicMetadataValueEnum::FloatValue(_) => self.module.get_function("max_float"),
-                node => unreachable!("impossible to call max with {}", node),
-            },
-            "min" => match args.first().unwrap() {
-                BasicMetadataValueEnum::IntValue(_) => self.module.get_function("min_int"),
-                BasicMetadataValueEnum::FloatValue(_) => self.module.get_function("min_float"),
-                node => unreachable!("impossible to call min with {}", node),
-            },
-            _ => self.module.get_function(name),
+        match self.variables.get(&ident.name) {
+            Some((ptr, ty)) => {
+                Ok(self
+                    .builder
+                    .build_load(ty.to_basic_type_enum(self), *ptr, &ident.name))
+            }
+            None => Err(IrError::Undefined(ident.name.clone())),
         }
     }
 
@@ -593,8 +970,8 @@ impl<'a, 'ctx> Ir<'a, 'ctx> {
             None => builder.position_at_end(entry),
         }
         match arg {
-            BasicValueEnum::IntValue(_) => builder.build_alloca(self.context.i64_type(), name),
-            BasicValueEnum::FloatValue(_) => builder.build_alloca(self.context.f64_type(), name),
+            BasicValueEnum::IntValue(i) => builder.build_alloca(i.get_type(), name),
+            BasicValueEnum::FloatValue(f) => builder.build_alloca(f.get_type(), name),
             BasicValueEnum::PointerValue(_) => builder.build_alloca(
                 self.context.i8_type().ptr_type(AddressSpace::default()),
                 name,
@@ -616,56 +993,46 @@ impl ToBasicTypeEnum for Type {
         match self {
             Type::Int => ir.context.i64_type().into(),
             Type::Float => ir.context.f64_type().into(),
+            Type::Integer(bits, _) => ir.context.custom_width_int_type(*bits).into(),
+            Type::F32 => ir.context.f32_type().into(),
             Type::Str => ir
                 .context
                 .i8_type()
                 .ptr_type(AddressSpace::default())
                 .into(),
             Type::Bool => ir.context.bool_type().into(),
-            // TODO: this may be something other than a class in the future
-            Type::UserDefined(name) => ir
+            Type::Char => ir.context.i32_type().into(),
+            Type::Class(cls, _) => ir
                 .classes
-                .get(name)
+                .get(&cls.name.to_string())
+                .copied()
                 .expect("called before all classes built")
-                .0
                 .into(),
+            Type::Interface(_) => unreachable!("interfaces are rejected before building"),
+            Type::Function(..) => ir
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::default())
+                .into(),
+            Type::Param(_, Some(bound)) => bound.to_basic_type_enum(ir),
+            Type::Param(name, None) => unimplemented!("unbounded type parameter `{name}`"),
+            // Only pointers can be null, so an optional is held like its inner type
+            Type::Optional(inner) => inner.to_basic_type_enum(ir),
+            Type::Nil => ir
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::default())
+                .into(),
+            Type::Tuple(_) | Type::Struct(..) | Type::Result(..) => {
+                let elements: Vec<_> = self
+                    .elements()
+                    .unwrap()
+                    .iter()
+                    .map(|element| element.to_basic_type_enum(ir))
+                    .collect();
+                ir.context.struct_type(&elements, false).into()
+            }
             Type::Void => unimplemented!("void does not implement `BasicTypeEnum`"),
         }
     }
 }
-
-impl ToBasicTypeEnum for Token {
-    fn to_basic_type_enum<'ctx>(&self, ir: &Ir<'_, 'ctx>) -> BasicTypeEnum<'ctx> {
-        Type::from(self).to_basic_type_enum(ir)
-    }
-}
-
-impl ToBasicTypeEnum for Option<Token> {
-    fn to_basic_type_enum<'ctx>(&self, ir: &Ir<'_, 'ctx>) -> BasicTypeEnum<'ctx> {
-        Type::from(self.as_ref()).to_basic_type_enum(ir)
-    }
-}
-
-fn main() -> node::Call {
-    node::Call::new(
-        Box::new(Ident::wrapped(Token {
-            kind: Kind::Identifier,
-            lexeme: Some("_main"),
-            span: Span::new(0, 0, 0),
-        })),
-        vec![],
-        (
-            Token {
-                kind: Kind::LeftParen,
-                lexeme: None,
-                span: Span::new(0, 0, 0),
-            },
-            Token {
-                kind: Kind::RightParen,
-                lexeme: None,
-                span: Span::new(0, 0, 0),
-            },
-        ),
-        vec![],
-    )
-}
@@ -5,14 +5,40 @@ const DEFINITIONS: &str = indoc::indoc! {"
     extern fun println_int(i: int)
     extern fun println_float(f: float)
     extern fun println_bool(b: bool)
+    extern fun println_char(c: char)
 
     extern fun max_int(a: int, b: int): int
     extern fun min_int(a: int, b: int): int
 
     extern fun max_float(a: float, b: float): float
     extern fun min_float(a: float, b: float): float
+
+    extern fun len(s: str): int
+    extern fun substring(s: str, start: int, end: int): str
+    extern fun int_to_str(i: int): str
+    extern fun float_to_str(f: float): str
+    extern fun bool_to_str(b: bool): str
+    extern fun char_to_str(c: char): str
+
+    extern fun str_concat(a: str, b: str): str
+    extern fun str_compare(a: str, b: str): int
+    extern fun str_index(s: str, index: int): char
+    % Only a for loop over a string calls these, with an index it has checked is within it
+    private extern fun str_char(s: str, index: int): char
+    private extern fun str_next(s: str, index: int): int
 "};
 
+/// The builtins that allocate strings. Besides their declared parameters, they're passed the
+/// frame pointer and frame size of the caller, so the garbage collector can scan its stack.
+pub const ALLOCATING: &[&str] = &[
+    "substring",
+    "int_to_str",
+    "float_to_str",
+    "bool_to_str",
+    "char_to_str",
+    "str_concat",
+];
+
 pub fn builtins() -> Ast {
     let source = Source::in_memory(DEFINITIONS.to_string());
     Ast::try_from(&source).unwrap()
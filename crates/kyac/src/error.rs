@@ -2,13 +2,14 @@ use crate::{token::Span, Source};
 use colored::Colorize;
 use std::fmt;
 
-#[derive(Debug)]
 pub struct PreciseError<'a> {
     filename: &'a str,
     heading: String,
     source: String,
     span: Span,
     text: String,
+    /// Another location that explains the error, such as a conflicting declaration
+    note: Option<Box<PreciseError<'a>>>,
 }
 
 impl<'a> PreciseError<'a> {
@@ -24,9 +25,16 @@ impl<'a> PreciseError<'a> {
             span,
             heading,
             text,
+            note: None,
         }
     }
 
+    #[must_use]
+    pub fn with_note(mut self, note: PreciseError<'a>) -> Self {
+        self.note = Some(Box::new(note));
+        self
+    }
+
     fn aligned<F>(&self, comment: &mut String, rest: F)
     where
         F: FnOnce(&mut String),
@@ -82,6 +90,33 @@ impl fmt::Display for PreciseError<'_> {
             "error".bold().red(),
             self.heading,
             self.build(),
-        )
+        )?;
+        if let Some(note) = &self.note {
+            write!(
+                f,
+                "{}: {}\n{}",
+                "note".bold().blue(),
+                note.heading,
+                note.build()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PreciseError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("PreciseError");
+        debug
+            .field("filename", &self.filename)
+            .field("heading", &self.heading)
+            .field("source", &self.source)
+            .field("span", &self.span)
+            .field("text", &self.text);
+        // Most errors have no note, so it is left out rather than shown as `None`
+        if let Some(note) = &self.note {
+            debug.field("note", note);
+        }
+        debug.finish()
     }
 }
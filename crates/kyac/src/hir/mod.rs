@@ -0,0 +1,122 @@
+pub mod node;
+pub mod ty;
+
+pub use ty::Type;
+
+use std::rc::Rc;
+
+/// The typed program produced by the type checker: every name is bound and every expression
+/// carries its type, so backends lower from here rather than from the AST.
+#[derive(Debug)]
+pub struct Hir {
+    pub nodes: Vec<Decl>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Decl {
+    Function(Rc<node::FuncDecl>),
+    Class(Rc<node::ClassDecl>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Var(Rc<node::VarDecl>),
+    Assign(Rc<node::Assign>),
+    Return(Rc<node::Return>),
+    Expr(Expr),
+    If(Rc<node::If>),
+    While(Rc<node::While>),
+    For(Rc<node::For>),
+    Destructure(Rc<node::Destructure>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Call(Rc<node::Call>),
+    Access(Rc<node::Access>),
+    Binary(Rc<node::Binary>),
+    Unary(Rc<node::Unary>),
+    Ident(Rc<node::Ident>),
+    Init(Rc<node::Init>),
+    Layout(Rc<node::Layout>),
+    Is(Rc<node::Is>),
+    Cast(Rc<node::Cast>),
+    Convert(Rc<node::Convert>),
+    Closure(Rc<node::Closure>),
+    Tuple(Rc<node::Tuple>),
+    Element(Rc<node::Element>),
+    Wrap(Rc<node::Wrap>),
+    Try(Rc<node::Try>),
+    Str(Rc<node::Literal<&'static str>>),
+    Int(Rc<node::Literal<i64>>),
+    Float(Rc<node::Literal<f64>>),
+    Bool(Rc<node::Literal<bool>>),
+    Char(Rc<node::Literal<char>>),
+    Nil(Rc<node::Literal<()>>),
+}
+
+impl Expr {
+    pub fn ty(&self) -> Type {
+        match self {
+            Expr::Call(call) => call.ty.clone(),
+            Expr::Access(access) => access.ty.clone(),
+            Expr::Binary(binary) => binary.ty.clone(),
+            Expr::Unary(unary) => unary.ty.clone(),
+            Expr::Ident(ident) => ident.ty.clone(),
+            Expr::Init(init) => init.ty.clone(),
+            Expr::Cast(cast) => cast.ty.clone(),
+            Expr::Convert(convert) => convert.ty.clone(),
+            Expr::Closure(closure) => closure.ty.clone(),
+            Expr::Tuple(tuple) => tuple.ty.clone(),
+            Expr::Element(element) => element.ty.clone(),
+            Expr::Wrap(wrap) => wrap.ty.clone(),
+            Expr::Try(t) => t.ty.clone(),
+            Expr::Str(_) => Type::Str,
+            Expr::Int(_) | Expr::Layout(_) => Type::Int,
+            Expr::Float(_) => Type::Float,
+            Expr::Char(_) => Type::Char,
+            Expr::Nil(_) => Type::Nil,
+            Expr::Is(_) | Expr::Bool(_) => Type::Bool,
+        }
+    }
+}
+
+macro_rules! assert_hir {
+    ($($path:expr => $name:ident),*) => {
+        #[cfg(test)]
+        mod tests {
+            use crate::{ast::Ast, hir::Decl, pass::{self, SymbolTable}, Source};
+
+            $(
+                #[test]
+                fn $name() -> Result<(), Box<dyn std::error::Error>> {
+                    let source = Source::new($path)?;
+                    let ast = Ast::try_from(&source)?;
+                    let symbols = SymbolTable::from(&ast.nodes);
+                    let hir = pass::resolve_types(&source, &symbols, &ast.nodes)
+                        .map_err(|errors| format!("{errors:?}"))?;
+                    // Builtins are left out, and so is the ID of each function, which depends on
+                    // the order the tests run in
+                    let functions: Vec<_> = hir
+                        .nodes
+                        .iter()
+                        .filter_map(|decl| match decl {
+                            Decl::Function(f) if !f.external => {
+                                Some((f.label(), &f.params, &f.ty, &f.captures, &f.body))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    insta::with_settings!({snapshot_path => "../../snapshots"}, {
+                        insta::assert_debug_snapshot!(functions);
+                    });
+                    Ok(())
+                }
+            )*
+        }
+    };
+}
+
+assert_hir!(
+    "test-cases/hir/functions.kya" => functions
+);
@@ -0,0 +1,704 @@
+use crate::{
+    ast,
+    hir::{Decl, Expr, Stmt, Type},
+    token::Kind,
+};
+use std::{collections::HashMap, rc::Rc};
+
+#[derive(Debug)]
+pub struct FuncDecl {
+    pub name: String,
+    pub class: Option<String>,
+    pub params: Vec<Param>,
+    pub ty: Type,
+    pub body: Vec<Stmt>,
+    pub external: bool,
+    pub id: usize,
+    /// For the body of a closure, the variables it captures. These are loaded from the closure
+    /// passed as the first parameter (named [`ENVIRONMENT`]) when the function is entered.
+    pub captures: Option<Vec<Param>>,
+}
+
+/// The name of the parameter that holds the closure a closure body was called through. It cannot
+/// be written in a program, so it never shadows a variable.
+pub const ENVIRONMENT: &str = "<env>";
+
+/// The name of the parameter that holds the type layout passed to a generic function. It cannot
+/// be written in a program, so it never shadows a variable.
+pub const TYPES: &str = "<types>";
+
+impl FuncDecl {
+    /// The symbol this function is emitted under: methods are prefixed with the name of their class.
+    pub fn label(&self) -> String {
+        match &self.class {
+            Some(class) => format!("{class}.{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ClassDecl {
+    pub name: String,
+    /// The type parameters of the class and its superclasses, in the order the bits of the type
+    /// layout of an instance follow
+    pub params: Vec<String>,
+    /// The resolved type of every field of the class, counting inherited fields
+    pub layout: Vec<Type>,
+    pub methods: Vec<Rc<FuncDecl>>,
+}
+
+impl ClassDecl {
+    pub fn wrapped(
+        name: String,
+        params: Vec<String>,
+        layout: Vec<Type>,
+        methods: Vec<Rc<FuncDecl>>,
+    ) -> Decl {
+        Decl::Class(Rc::new(Self {
+            name,
+            params,
+            layout,
+            methods,
+        }))
+    }
+
+    /// The layout of an instance instantiated with `arguments` as seen by the GC: `p` for a
+    /// pointer field, and otherwise `b`, `h` or `w` for a field of one, two or four bytes and `i`
+    /// for a whole word. A struct field is embedded in place, so each of its own fields has a
+    /// character of its own, and the GC finds the pointers it holds like any other.
+    ///
+    /// A field whose type is still an unbounded parameter (when allocating inside generic code)
+    /// is written as the capital letter of the parameter's index in `params`, and the GC looks up
+    /// whether it holds a pointer in the type layout of the instance. A parameter with a bound
+    /// always holds a pointer. The size of a field comes from its declared type, so a parameter
+    /// always takes a whole word whatever it is instantiated with.
+    pub fn descriptor(&self, arguments: &HashMap<String, Type>) -> String {
+        self.layout
+            .iter()
+            .flat_map(Type::leaves)
+            .map(|(_, ty)| match ty.substitute(arguments) {
+                Type::Param(name, None) => self
+                    .params
+                    .iter()
+                    .position(|param| *param == name)
+                    .and_then(|index| u8::try_from(index).ok())
+                    .filter(|&index| index < 26)
+                    .map_or('p', |index| char::from(b'A' + index)),
+                ty if ty.is_ptr() => 'p',
+                _ => match ty {
+                    Type::Integer(8, _) => 'b',
+                    Type::Integer(16, _) => 'h',
+                    Type::Integer(32, _) | Type::F32 | Type::Char => 'w',
+                    _ => 'i',
+                },
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct VarDecl {
+    pub name: String,
+    pub ty: Type,
+    pub expr: Expr,
+}
+
+impl VarDecl {
+    pub fn wrapped(name: String, ty: Type, expr: Expr) -> Stmt {
+        Stmt::Var(Rc::new(Self { name, ty, expr }))
+    }
+}
+
+/// A declaration of a variable for each element of the tuple `expr`, in order
+#[derive(Debug)]
+pub struct Destructure {
+    pub names: Vec<String>,
+    pub expr: Expr,
+}
+
+impl Destructure {
+    pub fn wrapped(names: Vec<String>, expr: Expr) -> Stmt {
+        Stmt::Destructure(Rc::new(Self { names, expr }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Assign {
+    pub target: Expr,
+    pub expr: Expr,
+}
+
+impl Assign {
+    pub fn wrapped(target: Expr, expr: Expr) -> Stmt {
+        Stmt::Assign(Rc::new(Self { target, expr }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Return {
+    pub expr: Expr,
+}
+
+impl Return {
+    pub fn wrapped(expr: Expr) -> Stmt {
+        Stmt::Return(Rc::new(Self { expr }))
+    }
+}
+
+#[derive(Debug)]
+pub struct If {
+    pub condition: Expr,
+    pub is: Vec<Stmt>,
+    pub otherwise: Vec<Stmt>,
+}
+
+impl If {
+    pub fn wrapped(condition: Expr, is: Vec<Stmt>, otherwise: Vec<Stmt>) -> Stmt {
+        Stmt::If(Rc::new(Self {
+            condition,
+            is,
+            otherwise,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct While {
+    pub condition: Expr,
+    pub body: Vec<Stmt>,
+}
+
+impl While {
+    pub fn wrapped(condition: Expr, body: Vec<Stmt>) -> Stmt {
+        Stmt::While(Rc::new(Self { condition, body }))
+    }
+}
+
+/// A loop that sets `index` to each value given by `iteration` in turn
+#[derive(Debug)]
+pub struct For {
+    pub index: String,
+    pub ty: Type,
+    pub iteration: Iteration,
+    pub body: Vec<Stmt>,
+    /// The prefix of the variables the loop keeps for itself, which cannot be written in a
+    /// program
+    pub hidden: String,
+}
+
+#[derive(Debug)]
+pub enum Iteration {
+    /// The integers from `start` towards `end`, `step` apart. The step is a constant other than
+    /// zero, so its sign decides whether the loop counts up or down.
+    Range {
+        start: Expr,
+        end: Expr,
+        step: i64,
+        inclusive: bool,
+    },
+    /// The characters of a string, found by their offsets in bytes
+    Chars(Expr),
+    /// The values `next` gives for as long as `has_next` is true. Both are calls on the iterator,
+    /// which is kept in the hidden variable named by [`For::variable`] with `"iterator"`.
+    Protocol {
+        iterator: Expr,
+        has_next: Expr,
+        next: Expr,
+    },
+}
+
+/// A `for` loop taken apart into the pieces of a `while` loop, which is how every backend
+/// emits it
+pub struct Loop {
+    /// Runs once, before the first test of the condition
+    pub setup: Vec<Stmt>,
+    pub condition: Expr,
+    /// Runs at the start of each iteration, before the body
+    pub enter: Vec<Stmt>,
+    /// Runs at the end of each iteration, after the body
+    pub advance: Vec<Stmt>,
+}
+
+impl For {
+    pub fn wrapped(
+        index: String,
+        ty: Type,
+        iteration: Iteration,
+        body: Vec<Stmt>,
+        hidden: String,
+    ) -> Stmt {
+        Stmt::For(Rc::new(Self {
+            index,
+            ty,
+            iteration,
+            body,
+            hidden,
+        }))
+    }
+
+    /// The name of a variable the loop with the `hidden` prefix keeps for itself
+    pub fn variable(hidden: &str, part: &str) -> String {
+        format!("{hidden}.{part}")
+    }
+
+    fn hidden(&self, part: &str, ty: Type) -> Expr {
+        Ident::wrapped(Self::variable(&self.hidden, part), ty)
+    }
+
+    /// Takes the loop apart. The end of a range is only evaluated once, and a string is walked by
+    /// byte offset, so each step only decodes the character at that offset.
+    pub fn lowered(&self) -> Loop {
+        let index = Ident::wrapped(self.index.clone(), self.ty.clone());
+        match &self.iteration {
+            Iteration::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            } => {
+                let mut setup = vec![VarDecl::wrapped(
+                    self.index.clone(),
+                    Type::Int,
+                    start.clone(),
+                )];
+                let end = if let Expr::Int(_) = end {
+                    end.clone()
+                } else {
+                    let name = Self::variable(&self.hidden, "end");
+                    setup.push(VarDecl::wrapped(name, Type::Int, end.clone()));
+                    self.hidden("end", Type::Int)
+                };
+                let op = match (*step > 0, inclusive) {
+                    (true, true) => Kind::LessEqual,
+                    (true, false) => Kind::Less,
+                    (false, true) => Kind::GreaterEqual,
+                    (false, false) => Kind::Greater,
+                };
+                let next = Binary::wrapped(
+                    index.clone(),
+                    Kind::Plus,
+                    Literal::<i64>::int(*step),
+                    Type::Int,
+                );
+                Loop {
+                    setup,
+                    condition: Binary::wrapped(index.clone(), op, end, Type::Bool),
+                    enter: vec![],
+                    advance: vec![Assign::wrapped(index, next)],
+                }
+            }
+            Iteration::Chars(string) => {
+                let s = self.hidden("string", Type::Str);
+                let length = self.hidden("length", Type::Int);
+                let offset = self.hidden("offset", Type::Int);
+                let call = |name: &str, ty: Type| {
+                    Call::wrapped(
+                        Callee::Function(String::from(name)),
+                        vec![s.clone(), offset.clone()],
+                        ty,
+                    )
+                };
+                Loop {
+                    setup: vec![
+                        VarDecl::wrapped(
+                            Self::variable(&self.hidden, "string"),
+                            Type::Str,
+                            string.clone(),
+                        ),
+                        VarDecl::wrapped(
+                            Self::variable(&self.hidden, "length"),
+                            Type::Int,
+                            Call::wrapped(
+                                Callee::Function(String::from("len")),
+                                vec![s.clone()],
+                                Type::Int,
+                            ),
+                        ),
+                        VarDecl::wrapped(
+                            Self::variable(&self.hidden, "offset"),
+                            Type::Int,
+                            Literal::<i64>::int(0),
+                        ),
+                    ],
+                    condition: Binary::wrapped(offset.clone(), Kind::Less, length, Type::Bool),
+                    enter: vec![VarDecl::wrapped(
+                        self.index.clone(),
+                        Type::Char,
+                        call("str_char", Type::Char),
+                    )],
+                    advance: vec![Assign::wrapped(offset.clone(), call("str_next", Type::Int))],
+                }
+            }
+            Iteration::Protocol {
+                iterator,
+                has_next,
+                next,
+            } => Loop {
+                setup: vec![VarDecl::wrapped(
+                    Self::variable(&self.hidden, "iterator"),
+                    iterator.ty(),
+                    iterator.clone(),
+                )],
+                condition: has_next.clone(),
+                enter: vec![VarDecl::wrapped(
+                    self.index.clone(),
+                    self.ty.clone(),
+                    next.clone(),
+                )],
+                advance: vec![],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Callee {
+    /// A free function, called by name
+    Function(String),
+    /// A method of `class`, called with the receiver as the first argument
+    Method {
+        class: Rc<ast::node::ClassDecl>,
+        name: String,
+    },
+    /// A method called directly by its label, bypassing dynamic dispatch
+    Static(String),
+    /// A method of `interface`, dispatched on the class of the receiver at runtime
+    Interface {
+        interface: Rc<ast::node::InterfaceDecl>,
+        name: String,
+    },
+    /// A function value, called through the closure it evaluates to
+    Closure(Expr),
+}
+
+#[derive(Debug)]
+pub struct Call {
+    pub callee: Callee,
+    pub args: Vec<Expr>,
+    pub ty: Type,
+}
+
+impl Call {
+    pub fn wrapped(callee: Callee, args: Vec<Expr>, ty: Type) -> Expr {
+        Expr::Call(Rc::new(Self { callee, args, ty }))
+    }
+}
+
+/// A read of the field at `index` (counting inherited fields) of `object`
+#[derive(Debug)]
+pub struct Access {
+    pub object: Expr,
+    pub field: usize,
+    pub ty: Type,
+}
+
+impl Access {
+    pub fn wrapped(object: Expr, field: usize, ty: Type) -> Expr {
+        Expr::Access(Rc::new(Self { object, field, ty }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Binary {
+    pub left: Expr,
+    pub op: Kind,
+    pub right: Expr,
+    pub ty: Type,
+}
+
+impl Binary {
+    pub fn wrapped(left: Expr, op: Kind, right: Expr, ty: Type) -> Expr {
+        Expr::Binary(Rc::new(Self {
+            left,
+            op,
+            right,
+            ty,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Unary {
+    pub op: Kind,
+    pub expr: Expr,
+    pub ty: Type,
+}
+
+impl Unary {
+    pub fn wrapped(op: Kind, expr: Expr, ty: Type) -> Expr {
+        Expr::Unary(Rc::new(Self { op, expr, ty }))
+    }
+}
+
+/// A test of whether the object `expr` is an instance of `class` (or one of its subclasses)
+#[derive(Debug)]
+pub struct Is {
+    pub expr: Expr,
+    pub class: String,
+}
+
+impl Is {
+    pub fn wrapped(expr: Expr, class: String) -> Expr {
+        Expr::Is(Rc::new(Self { expr, class }))
+    }
+}
+
+/// A downcast of the object `expr` to `ty`, which fails at runtime if the object is not an
+/// instance of the class `ty`
+#[derive(Debug)]
+pub struct Cast {
+    pub expr: Expr,
+    pub ty: Type,
+}
+
+impl Cast {
+    pub fn wrapped(expr: Expr, ty: Type) -> Expr {
+        Expr::Cast(Rc::new(Self { expr, ty }))
+    }
+}
+
+/// A conversion of the number `expr` to the numeric type `ty`, which wraps an integer that does
+/// not fit and truncates a float towards zero. A character converts to and from an integer
+/// holding its unicode scalar value.
+#[derive(Debug)]
+pub struct Convert {
+    pub expr: Expr,
+    pub ty: Type,
+}
+
+impl Convert {
+    pub fn wrapped(expr: Expr, ty: Type) -> Expr {
+        Expr::Convert(Rc::new(Self { expr, ty }))
+    }
+}
+
+/// The creation of a function value. `captures` are evaluated into a new closure along with the
+/// label of `function`, which expects the closure as its first argument.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: String,
+    pub captures: Vec<Expr>,
+    pub ty: Type,
+}
+
+impl Closure {
+    pub fn wrapped(function: String, captures: Vec<Expr>, ty: Type) -> Expr {
+        Expr::Closure(Rc::new(Self {
+            function,
+            captures,
+            ty,
+        }))
+    }
+
+    /// The layout of the closure as seen by the GC: `p` for a captured pointer, `i` otherwise.
+    pub fn descriptor(&self) -> String {
+        self.captures
+            .iter()
+            .map(|capture| if capture.ty().is_ptr() { 'p' } else { 'i' })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct Tuple {
+    pub elements: Vec<Expr>,
+    pub ty: Type,
+}
+
+impl Tuple {
+    pub fn wrapped(elements: Vec<Expr>) -> Expr {
+        let ty = Type::Tuple(elements.iter().map(Expr::ty).collect());
+        Expr::Tuple(Rc::new(Self { elements, ty }))
+    }
+
+    /// A value of the struct type `ty`, made of its fields in order.
+    pub fn structure(fields: Vec<Expr>, ty: Type) -> Expr {
+        Expr::Tuple(Rc::new(Self {
+            elements: fields,
+            ty,
+        }))
+    }
+}
+
+/// A read of the element at `index` of the tuple `tuple`
+#[derive(Debug)]
+pub struct Element {
+    pub tuple: Expr,
+    pub index: usize,
+    pub ty: Type,
+}
+
+impl Element {
+    pub fn wrapped(tuple: Expr, index: usize, ty: Type) -> Expr {
+        Expr::Element(Rc::new(Self { tuple, index, ty }))
+    }
+}
+
+/// A result made by `ok` (holding the value `value`) or `err` (holding the error `value`). Until it
+/// is coerced to the result type it is used as, the half of `ty` it does not hold is the type
+/// parameter `_`.
+#[derive(Debug)]
+pub struct Wrap {
+    pub ok: bool,
+    pub value: Expr,
+    pub ty: Type,
+}
+
+impl Wrap {
+    pub fn wrapped(ok: bool, value: Expr, ty: Type) -> Expr {
+        Expr::Wrap(Rc::new(Self { ok, value, ty }))
+    }
+}
+
+/// A read of the value of the result `expr`, of type `ty`. If it holds an error instead, the
+/// function returns early with a result of type `ret` holding the same error.
+#[derive(Debug)]
+pub struct Try {
+    pub expr: Expr,
+    pub ty: Type,
+    pub ret: Type,
+}
+
+impl Try {
+    pub fn wrapped(expr: Expr, ty: Type, ret: Type) -> Expr {
+        Expr::Try(Rc::new(Self { expr, ty, ret }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Ident {
+    pub name: String,
+    pub ty: Type,
+}
+
+impl Ident {
+    pub fn wrapped(name: String, ty: Type) -> Expr {
+        Expr::Ident(Rc::new(Self { name, ty }))
+    }
+}
+
+/// An allocation of a class, with initializers ordered by field index
+#[derive(Debug)]
+pub struct Init {
+    pub initializers: Vec<Initializer>,
+    /// The label of the class's `init` method, called with the object once its fields are set
+    pub method: Option<String>,
+    /// The type layout of the instance, for each of the class's `params`
+    pub layout: Layout,
+    pub ty: Type,
+}
+
+impl Init {
+    pub fn wrapped(
+        initializers: Vec<Initializer>,
+        method: Option<String>,
+        layout: Layout,
+        ty: Type,
+    ) -> Expr {
+        Expr::Init(Rc::new(Self {
+            initializers,
+            method,
+            layout,
+            ty,
+        }))
+    }
+}
+
+/// Which type arguments of an instantiation hold pointers, as an `int` with a bit for each. The GC
+/// needs it to trace the fields of a generic class, and a generic function is passed it (as its
+/// last parameter, named [`TYPES`]) to allocate with its own type parameters.
+#[derive(Debug)]
+pub struct Layout {
+    pub bits: Vec<Bit>,
+}
+
+impl Layout {
+    pub fn new(bits: Vec<Bit>) -> Self {
+        Self { bits }
+    }
+
+    pub fn wrapped(bits: Vec<Bit>) -> Expr {
+        Expr::Layout(Rc::new(Self::new(bits)))
+    }
+}
+
+/// Whether a type argument holds a pointer
+#[derive(Debug)]
+pub enum Bit {
+    /// A type argument that is known to hold a pointer or not
+    Known(bool),
+    /// A type parameter of the function being checked, which is bit `index` of the layout it was
+    /// passed in `expr`
+    Param(Expr, usize),
+    /// A type parameter of the class of the object `expr`, which is bit `index` of the layout
+    /// stored in the object
+    Object(Expr, usize),
+}
+
+#[derive(Debug)]
+pub struct Initializer {
+    pub field: usize,
+    pub expr: Expr,
+}
+
+impl Initializer {
+    pub fn new(field: usize, expr: Expr) -> Self {
+        Self { field, expr }
+    }
+}
+
+#[derive(Debug)]
+pub struct Literal<T> {
+    pub value: T,
+}
+
+impl<T> Literal<T> {
+    pub fn new(value: T) -> Self {
+        Self { value }
+    }
+
+    pub fn int(value: i64) -> Expr {
+        Expr::Int(Rc::new(Literal::new(value)))
+    }
+
+    pub fn float(value: f64) -> Expr {
+        Expr::Float(Rc::new(Literal::new(value)))
+    }
+
+    pub fn string(value: &'static str) -> Expr {
+        Expr::Str(Rc::new(Literal::new(value)))
+    }
+
+    pub fn bool(value: bool) -> Expr {
+        Expr::Bool(Rc::new(Literal::new(value)))
+    }
+
+    pub fn char(value: char) -> Expr {
+        Expr::Char(Rc::new(Literal::new(value)))
+    }
+
+    pub fn nil() -> Expr {
+        Expr::Nil(Rc::new(Literal::new(())))
+    }
+}
+
+impl Literal<&str> {
+    /// The characters of the string, without its delimiting quotes. Escape sequences have
+    /// already been replaced by the lexer.
+    pub fn contents(&self) -> &str {
+        &self.value[1..self.value.len() - 1]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+}
+
+impl Param {
+    pub fn new(name: String, ty: Type) -> Self {
+        Self { name, ty }
+    }
+}
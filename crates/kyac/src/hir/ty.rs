@@ -0,0 +1,303 @@
+use crate::ast::node::{ClassDecl, InterfaceDecl};
+use std::{collections::HashMap, fmt, rc::Rc};
+
+/// A type as understood by the type checker, after every name has been resolved.
+#[derive(Debug, Clone)]
+pub enum Type {
+    /// A signed 64-bit integer, also written `i64`
+    Int,
+    /// A 64-bit float
+    Float,
+    /// An integer of another width: its size in bits and whether it is signed
+    Integer(u32, bool),
+    /// A 32-bit float
+    F32,
+    Bool,
+    /// A unicode scalar value, held in 32 bits
+    Char,
+    Str,
+    Void,
+    /// An instance of a user-defined class, along with the types it was instantiated with
+    Class(Rc<ClassDecl>, Vec<Type>),
+    /// A value of any class that implements an interface
+    Interface(Rc<InterfaceDecl>),
+    /// A function value: the types of its parameters and its return type
+    Function(Vec<Type>, Box<Type>),
+    /// A fixed number of values held together, which live in registers and on the stack rather
+    /// than on the heap
+    Tuple(Vec<Type>),
+    /// A value of a struct, along with the type of each of its fields. Like a tuple, it lives in
+    /// registers and on the stack, and is embedded in place when it is a field of a class.
+    Struct(Rc<ClassDecl>, Vec<Type>),
+    /// Either a value of the inner type, which is held as a pointer, or `nil`
+    Optional(Box<Type>),
+    /// The type of `nil`, which can be used as a value of any optional type
+    Nil,
+    /// Either a value of the first type or an error of the second, held like a tuple of whether
+    /// it is a value, the value and the error
+    Result(Box<Type>, Box<Type>),
+    /// A type parameter, along with the class or interface that bounds it (if any)
+    Param(String, Option<Box<Type>>),
+}
+
+impl Type {
+    /// The class this type refers to, looking through the bound of a type parameter.
+    pub fn class(&self) -> Option<&Rc<ClassDecl>> {
+        match self {
+            Type::Class(cls, _) => Some(cls),
+            Type::Param(_, Some(bound)) => bound.class(),
+            _ => None,
+        }
+    }
+
+    /// The interface this type refers to, looking through the bound of a type parameter.
+    pub fn interface(&self) -> Option<&Rc<InterfaceDecl>> {
+        match self {
+            Type::Interface(interface) => Some(interface),
+            Type::Param(_, Some(bound)) => bound.interface(),
+            _ => None,
+        }
+    }
+
+    /// Whether values of this type are represented as a pointer to a heap allocation.
+    pub fn is_ptr(&self) -> bool {
+        !matches!(
+            self,
+            Type::Int
+                | Type::Float
+                | Type::Integer(..)
+                | Type::F32
+                | Type::Bool
+                | Type::Char
+                | Type::Void
+                | Type::Tuple(_)
+                | Type::Struct(..)
+                | Type::Result(..)
+        )
+    }
+
+    /// The types of the elements of a tuple, of the fields of a struct, or of the tuple that holds
+    /// a result. Any other type has no elements.
+    pub fn elements(&self) -> Option<Vec<Type>> {
+        match self {
+            Type::Tuple(elements) | Type::Struct(_, elements) => Some(elements.clone()),
+            Type::Result(value, error) => Some(vec![Type::Bool, *value.clone(), *error.clone()]),
+            _ => None,
+        }
+    }
+
+    /// The values a value of this type is made of when it is held in registers or on the stack,
+    /// each with the path of element indices that leads to it (such as `.1.0`). A tuple is held
+    /// as the values of its elements, and any other value as itself with an empty path.
+    pub fn leaves(&self) -> Vec<(String, Type)> {
+        match self.elements() {
+            Some(elements) => elements
+                .iter()
+                .enumerate()
+                .flat_map(|(i, element)| {
+                    element
+                        .leaves()
+                        .into_iter()
+                        .map(move |(path, ty)| (format!(".{i}{path}"), ty))
+                })
+                .collect(),
+            None => vec![(String::new(), self.clone())],
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Type::Int | Type::Integer(..))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Type::Float | Type::F32)
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::Integer(_, false))
+    }
+
+    /// The size in bits of a number or character of this type. Every other value takes a whole
+    /// word.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Type::Integer(bits, _) => *bits,
+            Type::F32 | Type::Char => 32,
+            _ => 64,
+        }
+    }
+
+    /// Whether `value` can be represented by this integer type.
+    pub fn holds(&self, value: i64) -> bool {
+        match self {
+            Type::Int => true,
+            Type::Integer(64, false) => value >= 0,
+            Type::Integer(bits, false) => (0..1 << bits).contains(&value),
+            Type::Integer(bits, true) => (-(1 << (bits - 1))..1 << (bits - 1)).contains(&value),
+            _ => false,
+        }
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Type::Bool)
+    }
+
+    pub fn is_primitive(&self) -> bool {
+        matches!(
+            self,
+            Type::Int
+                | Type::Float
+                | Type::Integer(..)
+                | Type::F32
+                | Type::Bool
+                | Type::Char
+                | Type::Str
+                | Type::Void
+        )
+    }
+
+    /// The type arguments of a class type, keyed by the name of the parameter they instantiate.
+    pub fn arguments(&self) -> HashMap<String, Type> {
+        match self {
+            Type::Class(cls, params) => cls
+                .tp
+                .iter()
+                .flatten()
+                .map(|tp| tp.name.to_string())
+                .zip(params.iter().cloned())
+                .collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Replaces every type parameter bound in `subst` with its type argument.
+    pub fn substitute(&self, subst: &HashMap<String, Type>) -> Type {
+        match self {
+            Type::Param(name, _) => subst.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Class(cls, params) => Type::Class(
+                Rc::clone(cls),
+                params.iter().map(|param| param.substitute(subst)).collect(),
+            ),
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| param.substitute(subst)).collect(),
+                Box::new(ret.substitute(subst)),
+            ),
+            Type::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| element.substitute(subst))
+                    .collect(),
+            ),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.substitute(subst))),
+            Type::Result(value, error) => Type::Result(
+                Box::new(value.substitute(subst)),
+                Box::new(error.substitute(subst)),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Binds the type parameters of `self` that are not yet in `subst` so that `self` matches
+    /// `got`. Parameters that `got` does not determine are left unbound.
+    pub fn infer(&self, got: &Type, subst: &mut HashMap<String, Type>) {
+        match (self, got) {
+            (Type::Param(name, _), _) => {
+                subst.entry(name.clone()).or_insert_with(|| got.clone());
+            }
+            (Type::Class(expected, params), Type::Class(cls, args))
+                if expected.name == cls.name =>
+            {
+                for (param, arg) in params.iter().zip(args) {
+                    param.infer(arg, subst);
+                }
+            }
+            (Type::Function(params, ret), Type::Function(args, got)) => {
+                for (param, arg) in params.iter().zip(args) {
+                    param.infer(arg, subst);
+                }
+                ret.infer(got, subst);
+            }
+            (Type::Tuple(elements), Type::Tuple(got)) => {
+                for (element, got) in elements.iter().zip(got) {
+                    element.infer(got, subst);
+                }
+            }
+            (Type::Optional(inner), Type::Optional(got)) => inner.infer(got, subst),
+            (Type::Result(value, error), Type::Result(got_value, got_error)) => {
+                value.infer(got_value, subst);
+                error.infer(got_error, subst);
+            }
+            // A value converts to an optional of its own type
+            (Type::Optional(inner), Type::Class(..)) => inner.infer(got, subst),
+            _ => {}
+        }
+    }
+}
+
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Type::Class(left, lp), Type::Class(right, rp)) => left.name == right.name && lp == rp,
+            (Type::Interface(left), Type::Interface(right)) => left.name == right.name,
+            (Type::Function(lp, left), Type::Function(rp, right)) => lp == rp && left == right,
+            (Type::Tuple(left), Type::Tuple(right)) => left == right,
+            (Type::Struct(left, _), Type::Struct(right, _)) => left.name == right.name,
+            (Type::Optional(left), Type::Optional(right)) => left == right,
+            (Type::Result(lv, le), Type::Result(rv, re)) => lv == rv && le == re,
+            (Type::Param(left, _), Type::Param(right, _)) => left == right,
+            (Type::Integer(lb, ls), Type::Integer(rb, rs)) => lb == rb && ls == rs,
+            (Type::Int, Type::Int)
+            | (Type::Float, Type::Float)
+            | (Type::F32, Type::F32)
+            | (Type::Bool, Type::Bool)
+            | (Type::Char, Type::Char)
+            | (Type::Str, Type::Str)
+            | (Type::Void, Type::Void)
+            | (Type::Nil, Type::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Integer(bits, true) => write!(f, "i{bits}"),
+            Type::Integer(bits, false) => write!(f, "u{bits}"),
+            Type::F32 => write!(f, "f32"),
+            Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
+            Type::Str => write!(f, "str"),
+            Type::Void => write!(f, "void"),
+            Type::Class(cls, params) if params.is_empty() => write!(f, "{}", cls.name),
+            Type::Class(cls, params) => {
+                let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+                write!(f, "{}<{}>", cls.name, params.join(", "))
+            }
+            Type::Interface(interface) => write!(f, "{}", interface.name),
+            Type::Struct(cls, _) => write!(f, "{}", cls.name),
+            Type::Function(params, ret) => {
+                let params: Vec<_> = params.iter().map(ToString::to_string).collect();
+                write!(f, "fun({})", params.join(", "))?;
+                match **ret {
+                    Type::Void => Ok(()),
+                    ref ret => write!(f, ": {ret}"),
+                }
+            }
+            Type::Tuple(elements) => {
+                let elements: Vec<_> = elements.iter().map(ToString::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+            Type::Optional(inner) => write!(f, "{inner}?"),
+            Type::Nil => write!(f, "nil"),
+            Type::Result(value, error) => write!(f, "Result<{value}, {error}>"),
+            Type::Param(name, _) => write!(f, "{name}"),
+        }
+    }
+}
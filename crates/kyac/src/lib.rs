@@ -2,6 +2,7 @@ mod ast;
 mod backend;
 mod builtins;
 mod error;
+mod hir;
 mod macros;
 mod parse;
 mod pass;
@@ -25,22 +26,19 @@ use std::{fs::File, io::Read, path::Path};
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn compile(source: &Source, backend: &Backend) -> Result<Output, PipelineError> {
-    #[cfg(feature = "llvm")]
-    let mut ast = ast::Ast::try_from(source)?;
-    #[cfg(not(feature = "llvm"))]
     let ast = ast::Ast::try_from(source)?;
     let symbols = SymbolTable::from(&ast.nodes);
-    let meta = pass::resolve_types(source, &symbols, &ast.nodes)
+    let hir = pass::resolve_types(source, &symbols, &ast.nodes)
         .map_err(|e| PipelineError::TypeError(e.len()))?;
     match backend {
         #[cfg(feature = "llvm")]
         Backend::Llvm => Ok(Output::Llvm(
-            llvm::Ir::build(&mut ast.nodes, symbols, meta).map_err(PipelineError::IrError)?,
+            llvm::Ir::build(&hir.nodes, &symbols).map_err(PipelineError::IrError)?,
         )),
         #[cfg(not(feature = "llvm"))]
         Backend::Llvm => panic!("LLVM backend not enabled"),
         Backend::Kyir => Ok(Output::Asm(kyir::asm::<A64, Armv8a>(
-            &ast.nodes, &symbols, &meta, false,
+            &hir.nodes, &symbols, false,
         ))),
     }
 }
@@ -19,8 +19,20 @@ pub enum ParseError {
     Expected(Kind, Span, Kind),
     #[error("unexpected {0}")]
     Unhandled(Kind, Span, &'static [Kind]),
+    #[error("{0} literal is out of range")]
+    OutOfRange(&'static str, Span),
+    #[error("repeated modifier {0}")]
+    Repeated(Kind, Span),
+    #[error("{0} cannot be used at the top level")]
+    TopLevel(Kind, Span),
 }
 
+/// The name, parameters, return type and type parameters of a function
+type Signature = (Token, Vec<Param>, Option<Type>, Vec<TypeParameter>);
+
+/// The arguments of a call, along with its parentheses and the commas between them
+type Arguments = (Vec<Expr>, (Token, Token), Vec<Token>);
+
 pub struct Parser<'a> {
     source: &'a Source,
     tokens: VecDeque<Token>,
@@ -43,11 +55,23 @@ impl<'a> Parser<'a> {
     pub fn parse(&mut self) -> Result<Vec<Decl>, &Vec<PreciseError<'a>>> {
         let mut nodes: Vec<Decl> = vec![];
         while let Ok(token) = self.peek() {
+            if token.kind == Kind::Eof {
+                break;
+            }
+            let visibility = self.top_level_visibility();
+            let (Ok(visibility), Ok(token)) = (visibility, self.peek()) else {
+                // A visibility modifier was left at the end of the file
+                let span = self.previous.as_ref().unwrap().span;
+                self.error(&ParseError::Expected(Kind::Fun, span, Kind::Eof));
+                break;
+            };
             match match token.kind {
-                Kind::Class => self.class(),
-                Kind::Fun => self.function(&None, false),
-                Kind::Extern => self.function(&None, true),
-                Kind::Const => self.constant(),
+                Kind::Class | Kind::Abstract | Kind::Struct => self.class(visibility),
+                Kind::Interface => self.interface(visibility),
+                Kind::Fun => self.function(false, visibility),
+                Kind::Extern => self.function(true, visibility),
+                Kind::Const => self.constant(visibility),
+                Kind::Type => self.alias(visibility),
                 Kind::Eof => break,
                 _ => {
                     let token = self.advance().unwrap();
@@ -72,29 +96,92 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn class(&mut self) -> Result<Decl, ParseError> {
-        self.consume(Kind::Class)?;
+    fn class(&mut self, visibility: Visibility) -> Result<Decl, ParseError> {
+        let r#struct = self.peek()?.kind == Kind::Struct;
+        let r#abstract = self.peek()?.kind == Kind::Abstract;
+        if r#struct {
+            self.consume(Kind::Struct)?;
+        } else {
+            if r#abstract {
+                self.consume(Kind::Abstract)?;
+                self.once(&[Kind::Abstract])?;
+            }
+            self.consume(Kind::Class)?;
+        }
         let name = self.consume(Kind::Identifier)?;
-        let tp = (self.peek()?.kind == Kind::Less)
+        // A struct is never generic and takes no part in inheritance
+        let tp = (!r#struct && self.peek()?.kind == Kind::Less)
             .then(|| self.type_parameters())
             .transpose()?;
-        let parent = (self.peek()?.kind == Kind::Colon)
+        let parent = (!r#struct && self.peek()?.kind == Kind::Colon)
             .then(|| {
                 self.consume(Kind::Colon)?;
                 self.consume(Kind::Identifier)
             })
             .transpose()?;
+        let mut interfaces = vec![];
+        if !r#struct && self.peek()?.kind == Kind::Implements {
+            self.consume(Kind::Implements)?;
+            interfaces.push(self.consume(Kind::Identifier)?);
+            while self.peek()?.kind == Kind::Comma {
+                self.consume(Kind::Comma)?;
+                interfaces.push(self.consume(Kind::Identifier)?);
+            }
+        }
         self.consume(Kind::LeftBrace)?;
         let fields = self.fields()?;
         let mut methods = vec![];
         while self.peek()?.kind != Kind::RightBrace {
-            methods.push(match self.function(&Some(name.clone()), false)? {
-                Decl::Function(fun) => fun,
-                _ => unreachable!(),
-            });
+            let visibility = self.visibility()?;
+            let r#static = self.peek()?.kind == Kind::Static;
+            if r#static {
+                self.consume(Kind::Static)?;
+                self.once(&[Kind::Static])?;
+            }
+            // A static method has no receiver, so its parameters are all declared with types
+            let (method, params, ty, tp) = self.signature((!r#static).then_some(&name))?;
+            // Only the methods of an abstract class may be declared without a body
+            let bodiless = r#abstract && !r#static && self.peek()?.kind != Kind::LeftBrace;
+            let body = if bodiless { vec![] } else { self.block()? };
+            methods.push(Rc::new(FuncDecl {
+                r#static,
+                visibility,
+                ..FuncDecl::new(method, params, ty, tp, body, false, bodiless)
+            }));
+        }
+        self.consume(Kind::RightBrace)?;
+        Ok(Decl::Class(Rc::new(ClassDecl {
+            name,
+            fields,
+            methods,
+            parent,
+            interfaces,
+            tp,
+            r#abstract,
+            r#struct,
+            visibility,
+        })))
+    }
+
+    fn interface(&mut self, visibility: Visibility) -> Result<Decl, ParseError> {
+        self.consume(Kind::Interface)?;
+        let name = self.consume(Kind::Identifier)?;
+        self.consume(Kind::LeftBrace)?;
+        let mut methods = vec![];
+        while self.peek()?.kind != Kind::RightBrace {
+            let (method, params, ty, tp) = self.signature(Some(&name))?;
+            methods.push(Rc::new(FuncDecl::new(
+                method,
+                params,
+                ty,
+                tp,
+                vec![],
+                false,
+                true,
+            )));
         }
         self.consume(Kind::RightBrace)?;
-        Ok(ClassDecl::wrapped(name, fields, methods, parent, tp))
+        Ok(InterfaceDecl::wrapped(name, methods, visibility))
     }
 
     fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, ParseError> {
@@ -117,36 +204,39 @@ impl<'a> Parser<'a> {
         Ok(tp)
     }
 
-    fn function(&mut self, method: &Option<Token>, external: bool) -> Result<Decl, ParseError> {
+    fn function(&mut self, external: bool, visibility: Visibility) -> Result<Decl, ParseError> {
         if external {
             self.consume(Kind::Extern)?;
         }
+        let (name, params, ty, tp) = self.signature(None)?;
+        let body = if external { vec![] } else { self.block()? };
+        Ok(FuncDecl::wrapped(
+            name, params, ty, tp, body, external, visibility,
+        ))
+    }
+
+    fn signature(&mut self, method: Option<&Token>) -> Result<Signature, ParseError> {
         self.consume(Kind::Fun)?;
-        let name = self.consume(Kind::Identifier)?;
+        // A class may declare an `init` method, which runs when one of its objects is initialized
+        let name = if method.is_some() && self.peek()?.kind == Kind::Init {
+            let init = self.consume(Kind::Init)?;
+            Token::new(Kind::Identifier, Some("init"), init.span)
+        } else {
+            self.consume(Kind::Identifier)?
+        };
         let tp = (self.peek()?.kind == Kind::Less)
             .then(|| self.type_parameters())
             .transpose()?
             .unwrap_or(vec![]);
         self.consume(Kind::LeftParen)?;
-        let params = self.params(method)?;
+        let params = self.params(&method.cloned())?;
         self.consume(Kind::RightParen)?;
         let mut ty: Option<Type> = None;
         if self.peek()?.kind == Kind::Colon {
             self.consume(Kind::Colon)?;
             ty = Some(self.ty()?);
         }
-        if external {
-            Ok(FuncDecl::wrapped(name, params, ty, tp, vec![], external))
-        } else {
-            Ok(FuncDecl::wrapped(
-                name,
-                params,
-                ty,
-                tp,
-                self.block()?,
-                external,
-            ))
-        }
+        Ok((name, params, ty, tp))
     }
 
     fn params(&mut self, method: &Option<Token>) -> Result<Vec<Param>, ParseError> {
@@ -171,19 +261,79 @@ impl<'a> Parser<'a> {
 
     fn fields(&mut self) -> Result<Vec<Field>, ParseError> {
         let mut fields: Vec<Field> = vec![];
-        while !matches!(self.peek()?.kind, Kind::RightBrace | Kind::Fun) {
+        while !self.methods_next() {
+            let visibility = self.visibility()?;
             let name = self.consume(Kind::Identifier)?;
             self.consume(Kind::Colon)?;
             let ty = self.ty()?;
-            fields.push(Field::new(name, ty));
-            if !matches!(self.peek()?.kind, Kind::RightBrace | Kind::Fun) {
+            let default = (self.peek()?.kind == Kind::Equal)
+                .then(|| {
+                    self.consume(Kind::Equal)?;
+                    self.expression()
+                })
+                .transpose()?;
+            fields.push(Field::new(name, ty, default, visibility));
+            if !self.methods_next() {
                 self.consume(Kind::Comma)?;
             }
         }
         Ok(fields)
     }
 
+    /// Whether the fields of a class body have ended, leaving its methods (if any) to be parsed.
+    fn methods_next(&self) -> bool {
+        // Repeated modifiers are reported once the member is parsed
+        let modifiers = self
+            .tokens
+            .iter()
+            .take_while(|token| matches!(token.kind, Kind::Pub | Kind::Protected | Kind::Private))
+            .count();
+        self.tokens.get(modifiers).map_or(true, |token| {
+            matches!(token.kind, Kind::RightBrace | Kind::Fun | Kind::Static)
+        })
+    }
+
+    /// Parses the visibility modifier of a field or method, which is public without one.
+    fn visibility(&mut self) -> Result<Visibility, ParseError> {
+        let visibility = match self.peek()?.kind {
+            Kind::Protected => Visibility::Protected,
+            Kind::Private => Visibility::Private,
+            Kind::Pub => Visibility::Public,
+            _ => return Ok(Visibility::Public),
+        };
+        self.advance();
+        // A member has a single visibility, whether or not it is the same one again
+        self.once(&[Kind::Pub, Kind::Protected, Kind::Private])?;
+        Ok(visibility)
+    }
+
+    /// Parses the visibility modifier of a top-level declaration, which is public without one.
+    /// There are no subclasses of a module, so a top-level declaration cannot be protected.
+    fn top_level_visibility(&mut self) -> Result<Visibility, ParseError> {
+        let token = self.peek()?.clone();
+        let visibility = self.visibility()?;
+        if visibility == Visibility::Protected {
+            // The declaration is still parsed, so that any errors within it are reported too
+            self.error(&ParseError::TopLevel(token.kind, token.span));
+        }
+        Ok(visibility)
+    }
+
     fn ty(&mut self) -> Result<Type, ParseError> {
+        let ty = match self.peek()?.kind {
+            Kind::Fun => self.function_type()?,
+            Kind::LeftParen => self.tuple_type()?,
+            _ => self.named_type()?,
+        };
+        if self.peek()?.kind == Kind::Question {
+            let question = self.consume(Kind::Question)?;
+            return Ok(Type::optional(question, ty));
+        }
+        Ok(ty)
+    }
+
+    /// Parses the name of a type, along with its type arguments if it has any.
+    fn named_type(&mut self) -> Result<Type, ParseError> {
         let base = self.consume(Kind::Identifier)?;
         (self.peek()?.kind == Kind::Less)
             .then(|| {
@@ -202,6 +352,57 @@ impl<'a> Parser<'a> {
             .map(|params| Type::new(base, params.unwrap_or_default()))
     }
 
+    fn function_type(&mut self) -> Result<Type, ParseError> {
+        let keyword = self.consume(Kind::Fun)?;
+        self.consume(Kind::LeftParen)?;
+        let mut params = vec![];
+        while self.peek()?.kind != Kind::RightParen {
+            params.push(self.ty()?);
+            if self.peek()?.kind != Kind::RightParen {
+                self.consume(Kind::Comma)?;
+            }
+        }
+        self.consume(Kind::RightParen)?;
+        let ret = (self.peek()?.kind == Kind::Colon)
+            .then(|| {
+                self.consume(Kind::Colon)?;
+                self.ty()
+            })
+            .transpose()?;
+        Ok(Type::function(keyword, params, ret))
+    }
+
+    /// Parses a tuple type such as `(int, str)`. A single type in parentheses is just that type.
+    fn tuple_type(&mut self) -> Result<Type, ParseError> {
+        let paren = self.consume(Kind::LeftParen)?;
+        let mut elements = vec![self.ty()?];
+        while self.peek()?.kind == Kind::Comma {
+            self.consume(Kind::Comma)?;
+            elements.push(self.ty()?);
+        }
+        self.consume(Kind::RightParen)?;
+        if elements.len() == 1 {
+            return Ok(elements.pop().unwrap());
+        }
+        Ok(Type::tuple(paren, elements))
+    }
+
+    fn lambda(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.consume(Kind::Fun)?;
+        self.consume(Kind::LeftParen)?;
+        let params = self.params(&None)?;
+        self.consume(Kind::RightParen)?;
+        let ty = (self.peek()?.kind == Kind::Colon)
+            .then(|| {
+                self.consume(Kind::Colon)?;
+                self.ty()
+            })
+            .transpose()?;
+        let body = self.block()?;
+        let function = FuncDecl::new(keyword.clone(), params, ty, vec![], body, false, false);
+        Ok(Lambda::wrapped(keyword, function))
+    }
+
     fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
         self.consume(Kind::LeftBrace)?;
         let mut stmts: Vec<Stmt> = vec![];
@@ -219,7 +420,7 @@ impl<'a> Parser<'a> {
         Ok(stmts)
     }
 
-    fn constant(&mut self) -> Result<Decl, ParseError> {
+    fn constant(&mut self, visibility: Visibility) -> Result<Decl, ParseError> {
         self.consume(Kind::Const)?;
         let name = self.consume(Kind::Identifier)?;
         self.consume(Kind::Colon)?;
@@ -227,18 +428,62 @@ impl<'a> Parser<'a> {
         self.consume(Kind::Equal)?;
         let value = self.expression()?;
         self.consume(Kind::Semicolon)?;
-        Ok(ConstantDecl::wrapped(name, ty, value))
+        Ok(ConstantDecl::wrapped(name, ty, value, visibility))
+    }
+
+    fn alias(&mut self, visibility: Visibility) -> Result<Decl, ParseError> {
+        self.consume(Kind::Type)?;
+        let name = self.consume(Kind::Identifier)?;
+        let tp = (self.peek()?.kind == Kind::Less)
+            .then(|| self.type_parameters())
+            .transpose()?
+            .unwrap_or_default();
+        self.consume(Kind::Equal)?;
+        let ty = self.ty()?;
+        self.consume(Kind::Semicolon)?;
+        Ok(AliasDecl::wrapped(name, tp, ty, visibility))
     }
 
     fn declaration(&mut self) -> Result<Stmt, ParseError> {
-        self.consume(Kind::Let)?;
+        // A variable declared with `var` or `let mut` can be assigned to, and one declared with
+        // `let` cannot
+        let mutable = if self.peek()?.kind == Kind::Var {
+            self.consume(Kind::Var)?;
+            true
+        } else {
+            self.consume(Kind::Let)?;
+            let mutable = self.peek()?.kind == Kind::Mut;
+            if mutable {
+                self.consume(Kind::Mut)?;
+            }
+            mutable
+        };
+        if self.peek()?.kind == Kind::LeftParen {
+            return self.destructure(mutable);
+        }
         let name = self.consume(Kind::Identifier)?;
         self.consume(Kind::Colon)?;
         let ty = self.ty()?;
         self.consume(Kind::Equal)?;
         let expr = self.expression()?;
         self.consume(Kind::Semicolon)?;
-        Ok(Stmt::Var(Rc::new(VarDecl { name, ty, expr })))
+        Ok(VarDecl::wrapped(name, ty, expr, mutable))
+    }
+
+    /// Parses the rest of `let (a, b) = expr;`, which declares a variable for each element of a
+    /// tuple. The types of the variables are those of the elements.
+    fn destructure(&mut self, mutable: bool) -> Result<Stmt, ParseError> {
+        self.consume(Kind::LeftParen)?;
+        let mut names = vec![self.consume(Kind::Identifier)?];
+        while self.peek()?.kind == Kind::Comma {
+            self.consume(Kind::Comma)?;
+            names.push(self.consume(Kind::Identifier)?);
+        }
+        self.consume(Kind::RightParen)?;
+        self.consume(Kind::Equal)?;
+        let expr = self.expression()?;
+        self.consume(Kind::Semicolon)?;
+        Ok(Destructure::wrapped(names, expr, mutable))
     }
 
     fn condition(&mut self) -> Result<Stmt, ParseError> {
@@ -258,9 +503,13 @@ impl<'a> Parser<'a> {
         self.consume(Kind::For)?;
         let index = self.consume(Kind::Identifier)?;
         self.consume(Kind::In)?;
-        let range = self.range()?;
+        let iter = if self.peek()?.kind == Kind::LeftBracket {
+            self.range()?
+        } else {
+            self.expression()?
+        };
         let block = self.block()?;
-        Ok(For::wrapped(index, range, block))
+        Ok(For::wrapped(index, iter, block))
     }
 
     fn r#while(&mut self) -> Result<Stmt, ParseError> {
@@ -272,7 +521,7 @@ impl<'a> Parser<'a> {
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
         match self.peek()?.kind {
-            Kind::Let => self.declaration(),
+            Kind::Let | Kind::Var => self.declaration(),
             Kind::If => self.condition(),
             Kind::For => self.r#for(),
             Kind::While => self.r#while(),
@@ -308,8 +557,19 @@ impl<'a> Parser<'a> {
         let start = self.expression()?;
         self.consume(Kind::Comma)?;
         let end = self.expression()?;
-        let right = self.consume(Kind::RightBracket)?;
-        Ok(Range::wrapped(start, end, (left, right)))
+        let right = if self.peek()?.kind == Kind::RightParen {
+            self.consume(Kind::RightParen)?
+        } else {
+            self.consume(Kind::RightBracket)?
+        };
+        // `by` is only a keyword here, so it stays usable as a name
+        let step = if self.peek()?.kind == Kind::Identifier && self.peek()?.lexeme == Some("by") {
+            self.advance();
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        Ok(Range::wrapped(start, end, step, (left, right)))
     }
 
     fn equality(&mut self) -> Result<Expr, ParseError> {
@@ -326,9 +586,13 @@ impl<'a> Parser<'a> {
         let mut expr = self.term()?;
         while matches!(
             self.peek()?.kind,
-            Kind::Greater | Kind::GreaterEqual | Kind::Less | Kind::LessEqual
+            Kind::Greater | Kind::GreaterEqual | Kind::Less | Kind::LessEqual | Kind::Is
         ) {
             let operator = self.advance().unwrap();
+            if operator.kind == Kind::Is {
+                expr = Is::wrapped(expr, operator, self.ty()?);
+                continue;
+            }
             let right = self.term()?;
             expr = Binary::wrapped(expr, operator, right);
         }
@@ -356,14 +620,41 @@ impl<'a> Parser<'a> {
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
-        Ok(match self.peek()?.kind {
+        let mut expr = match self.peek()?.kind {
+            // A negated number is a single literal, so that the most negative `int` can be written
+            Kind::Minus if self.tokens.get(1).is_some_and(Self::numeric) => {
+                let minus = self.advance().unwrap();
+                let token = self.advance().unwrap();
+                Self::number(token, Some(minus))?
+            }
             Kind::Bang | Kind::Minus => {
                 let operator = self.advance().unwrap();
                 let right = self.unary()?;
-                Unary::wrapped(operator, right)
+                return Ok(Unary::wrapped(operator, right));
             }
-            _ => self.access()?,
-        })
+            _ => self.index()?,
+        };
+        while self.peek()?.kind == Kind::As {
+            let keyword = self.consume(Kind::As)?;
+            expr = Cast::wrapped(expr, keyword, self.ty()?);
+        }
+        Ok(expr)
+    }
+
+    fn index(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.access()?;
+        loop {
+            match self.peek()?.kind {
+                Kind::LeftBracket => {
+                    let open = self.consume(Kind::LeftBracket)?;
+                    let index = self.expression()?;
+                    let close = self.consume(Kind::RightBracket)?;
+                    expr = Index::wrapped(expr, index, (open, close));
+                }
+                Kind::Question => expr = Try::wrapped(expr, self.consume(Kind::Question)?),
+                _ => return Ok(expr),
+            }
+        }
     }
 
     fn access(&mut self) -> Result<Expr, ParseError> {
@@ -397,45 +688,87 @@ impl<'a> Parser<'a> {
     fn call(&mut self) -> Result<Expr, ParseError> {
         let mut left = self.primary()?;
         if self.peek()?.kind == Kind::LeftParen {
-            let open = self.consume(Kind::LeftParen)?;
-            let mut args: Vec<Expr> = vec![];
-            let mut delimiters: Vec<Token> = vec![];
-            if self.peek()?.kind != Kind::RightParen {
+            let (args, parens, delimiters) = self.arguments()?;
+            left = Call::wrapped(left, args, parens, delimiters);
+        }
+        Ok(left)
+    }
+
+    fn arguments(&mut self) -> Result<Arguments, ParseError> {
+        let open = self.consume(Kind::LeftParen)?;
+        let mut args: Vec<Expr> = vec![];
+        let mut delimiters: Vec<Token> = vec![];
+        if self.peek()?.kind != Kind::RightParen {
+            args.push(self.expression()?);
+            while self.peek()?.kind == Kind::Comma {
+                delimiters.push(self.consume(Kind::Comma)?);
                 args.push(self.expression()?);
-                while self.peek()?.kind == Kind::Comma {
-                    delimiters.push(self.consume(Kind::Comma)?);
-                    args.push(self.expression()?);
-                }
             }
-            let close = self.consume(Kind::RightParen)?;
-            left = Call::wrapped(left, args, (open, close), delimiters);
         }
-        Ok(left)
+        let close = self.consume(Kind::RightParen)?;
+        Ok((args, (open, close), delimiters))
+    }
+
+    /// Parses the rest of a string with interpolated expressions, after its first literal. The lexer
+    /// follows every interpolated expression with another literal.
+    fn interpolation(&mut self, first: Expr) -> Result<Expr, ParseError> {
+        let mut parts = vec![first];
+        while self.peek()?.kind == Kind::InterpolationStart {
+            self.consume(Kind::InterpolationStart)?;
+            parts.push(self.expression()?);
+            self.consume(Kind::InterpolationEnd)?;
+            let token = self.consume(Kind::Literal)?;
+            parts.push(Literal::<&str>::string(token.lexeme.unwrap(), token));
+        }
+        Ok(Interpolation::wrapped(parts))
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         Ok(match self.peek()?.kind {
             Kind::LeftParen => {
-                self.consume(Kind::LeftParen)?;
+                let open = self.consume(Kind::LeftParen)?;
                 let expr = self.expression()?;
-                self.consume(Kind::RightParen)?;
-                expr
+                if self.peek()?.kind != Kind::Comma {
+                    self.consume(Kind::RightParen)?;
+                    return Ok(expr);
+                }
+                let mut elements = vec![expr];
+                while self.peek()?.kind == Kind::Comma {
+                    self.consume(Kind::Comma)?;
+                    elements.push(self.expression()?);
+                }
+                let close = self.consume(Kind::RightParen)?;
+                Tuple::wrapped(elements, (open, close))
             }
             Kind::Literal => {
                 let token = self.advance().unwrap();
                 let lexeme = token.lexeme.unwrap();
                 match lexeme {
                     "true" | "false" => Literal::<bool>::bool(lexeme == "true", token),
-                    _ if lexeme.starts_with('"') => Literal::<&str>::string(lexeme, token),
-                    _ if lexeme.contains('.') => {
-                        Literal::<f64>::float(lexeme.parse().unwrap(), token)
+                    _ if lexeme.starts_with('"') => {
+                        let string = Literal::<&str>::string(lexeme, token);
+                        if self.peek()?.kind == Kind::InterpolationStart {
+                            self.interpolation(string)?
+                        } else {
+                            string
+                        }
                     }
-                    _ if lexeme.chars().next().unwrap().is_ascii_digit() => {
-                        Literal::<i64>::int(lexeme.parse().unwrap(), token)
+                    _ if lexeme.starts_with('\'') => {
+                        Literal::<char>::char(lexeme.chars().nth(1).unwrap(), token)
                     }
+                    _ if Self::numeric(&token) => Self::number(token, None)?,
                     e => unreachable!("impossible lexeme `{}`", e),
                 }
             }
+            Kind::Nil => Literal::<()>::nil(self.advance().unwrap()),
+            Kind::Fun => self.lambda()?,
+            Kind::Super => {
+                let keyword = self.consume(Kind::Super)?;
+                self.consume(Kind::Dot)?;
+                let method = self.consume(Kind::Identifier)?;
+                let (args, parens, delimiters) = self.arguments()?;
+                Super::wrapped(keyword, method, args, parens, delimiters)
+            }
             Kind::Identifier => {
                 let name = self.advance().unwrap();
                 if self.peek()?.kind == Kind::Colon {
@@ -452,6 +785,51 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn numeric(token: &Token) -> bool {
+        token.kind == Kind::Literal
+            && token
+                .lexeme
+                .is_some_and(|lexeme| lexeme.starts_with(|c: char| c.is_ascii_digit()))
+    }
+
+    /// Converts a numeric literal, negated if it follows a `-`. An integer with a radix prefix is
+    /// never a float, even if it contains an `e`.
+    fn number(token: Token, minus: Option<Token>) -> Result<Expr, ParseError> {
+        let lexeme = token.lexeme.unwrap();
+        let (token, sign) = match minus {
+            Some(minus) => {
+                let end = token.span.column + token.span.length;
+                let span = Span::new(
+                    minus.span.line,
+                    minus.span.column,
+                    end.saturating_sub(minus.span.column),
+                );
+                let lexeme = format!("-{lexeme}").leak();
+                (Token::new(Kind::Literal, Some(lexeme), span), "-")
+            }
+            None => (token, ""),
+        };
+        let digits: String = lexeme.chars().filter(|&c| c != '_').collect();
+        let radix = match digits.get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0o" | "0O") => 8,
+            Some("0b" | "0B") => 2,
+            _ => 10,
+        };
+        if radix == 10 && digits.contains(['.', 'e', 'E']) {
+            let value: f64 = format!("{sign}{digits}").parse().unwrap();
+            if value.is_infinite() {
+                return Err(ParseError::OutOfRange("float", token.span));
+            }
+            return Ok(Literal::<f64>::float(value, token));
+        }
+        let digits = if radix == 10 { &digits } else { &digits[2..] };
+        match i64::from_str_radix(&format!("{sign}{digits}"), radix) {
+            Ok(value) => Ok(Literal::<i64>::int(value, token)),
+            Err(_) => Err(ParseError::OutOfRange("integer", token.span)),
+        }
+    }
+
     fn init(&mut self, name: Token) -> Result<Expr, ParseError> {
         self.consume(Kind::Colon)?;
         self.consume(Kind::Init)?;
@@ -505,12 +883,25 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Reports and skips any of the modifiers `kinds` directly after one of them was consumed,
+    /// so that the rest of the declaration is still parsed.
+    fn once(&mut self, kinds: &[Kind]) -> Result<(), ParseError> {
+        while kinds.contains(&self.peek()?.kind) {
+            let token = self.advance().unwrap();
+            self.error(&ParseError::Repeated(token.kind, token.span));
+        }
+        Ok(())
+    }
+
     fn error(&mut self, e: &ParseError) {
         self.panic = true;
         let span = *match &e {
             ParseError::Unhandled(_, span, _)
             | ParseError::UnexpectedEof(span)
-            | ParseError::Expected(_, span, _) => span,
+            | ParseError::Expected(_, span, _)
+            | ParseError::OutOfRange(_, span)
+            | ParseError::Repeated(_, span)
+            | ParseError::TopLevel(_, span) => span,
         };
         let detail = match e {
             ParseError::Expected(expected, _, _) => format!("expected {expected} here"),
@@ -523,6 +914,15 @@ impl<'a> Parser<'a> {
                 format!("expected one of {expected} here")
             }
             ParseError::UnexpectedEof(_) => "unexpected end of file".into(),
+            ParseError::OutOfRange("float", _) => "does not fit in a `float`".into(),
+            ParseError::OutOfRange(..) => "does not fit in an `int`".into(),
+            ParseError::Repeated(Kind::Pub | Kind::Protected | Kind::Private, _) => {
+                "a declaration can only be given one visibility; try removing it".into()
+            }
+            ParseError::Repeated(kind, _) => format!("`{kind}` was already given; try removing it"),
+            ParseError::TopLevel(..) => {
+                "only the members of a class can be protected; try `private`".into()
+            }
         };
         let error = PreciseError::new(self.source, span, format!("{e}"), detail);
         println!("{error}");
@@ -542,7 +942,7 @@ impl<'a> Parser<'a> {
             }
             if matches!(
                 self.peek().unwrap().kind,
-                Kind::Let | Kind::Fun | Kind::Const
+                Kind::Let | Kind::Var | Kind::Fun | Kind::Const | Kind::Type
             ) {
                 return;
             }
@@ -592,8 +992,15 @@ assert_parse! {
     "test-cases/empty.kya" => empty / true,
     "test-cases/access.kya" => access / true,
     "test-cases/mixed.kya" => mixed / true,
+    "test-cases/numbers.kya" => numbers / true,
+    "test-cases/chars.kya" => chars / true,
+    "test-cases/tuples.kya" => tuples / true,
+    "test-cases/optionals.kya" => optionals / true,
+    "test-cases/results.kya" => results / true,
 
     "test-cases/parser/simple.kya" => simple / false,
     "test-cases/parser/toplevel.kya" => toplevel / false,
-    "test-cases/parser/nested.kya" => nested / false
+    "test-cases/parser/nested.kya" => nested / false,
+    "test-cases/parser/overflow.kya" => overflow / false,
+    "test-cases/parser/modifiers.kya" => modifiers / false
 }
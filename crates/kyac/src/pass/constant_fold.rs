@@ -1,73 +1,77 @@
 use std::ops::{Add, Div, Mul, Sub};
 
 use crate::{
-    ast::{
+    hir::{
         node::{self, Literal},
         Expr,
     },
-    token::{Kind, Span, Token},
+    token::Kind,
 };
 
 impl node::Binary {
+    /// Folds arithmetic on literals into a single literal. Integer operations that would overflow
+    /// or divide by zero are left to be evaluated (and fail) at runtime.
     pub fn fold(&self) -> Expr {
-        let ignored =
-            || node::Binary::wrapped(*self.left.clone(), self.op.clone(), *self.right.clone());
-        let token = Token::new(Kind::Literal, None, Span::default());
-        match (&*self.left, &*self.right) {
-            (Expr::Int(left), Expr::Int(right)) => {
-                Literal::<i64>::int(apply(left.value, right.value, self.op.kind), token)
-            }
-            (Expr::Float(left), Expr::Float(right)) => {
-                Literal::<f64>::float(apply(left.value, right.value, self.op.kind), token)
-            }
-            (Expr::Binary(left), Expr::Int(right)) => {
-                let left = left.fold();
-                match left {
-                    Expr::Int(literal) => {
-                        Literal::<i64>::int(apply(literal.value, right.value, self.op.kind), token)
-                    }
-                    _ => ignored(),
-                }
-            }
-            (Expr::Int(left), Expr::Binary(right)) => {
-                let right = right.fold();
-                match right {
-                    Expr::Int(literal) => {
-                        Literal::<i64>::int(apply(left.value, literal.value, self.op.kind), token)
-                    }
-                    _ => ignored(),
+        let left = fold(&self.left);
+        let right = fold(&self.right);
+        match (&left, &right) {
+            (Expr::Int(l), Expr::Int(r)) => {
+                if let Some(value) = checked(l.value, r.value, self.op) {
+                    return Literal::<i64>::int(value);
                 }
             }
-            (Expr::Binary(left), Expr::Binary(right)) => {
-                let left = left.fold();
-                let right = right.fold();
-                match (&left, &right) {
-                    (Expr::Int(left), Expr::Int(right)) => Literal::<i64>::int(
-                        apply(left.value, right.value, self.op.kind),
-                        Token::new(Kind::Literal, None, Span::default()),
-                    ),
-                    (Expr::Float(left), Expr::Float(right)) => Literal::<f64>::float(
-                        apply(left.value, right.value, self.op.kind),
-                        Token::new(Kind::Literal, None, Span::default()),
-                    ),
-                    _ => node::Binary::wrapped(left, self.op.clone(), right),
+            (Expr::Float(l), Expr::Float(r)) => {
+                if let Some(value) = apply(l.value, r.value, self.op) {
+                    return Literal::<f64>::float(value);
                 }
             }
-            _ => ignored(),
+            _ => {}
+        }
+        node::Binary::wrapped(left, self.op, right, self.ty.clone())
+    }
+}
+
+impl node::Unary {
+    /// Folds a negated number or an inverted boolean into a single literal.
+    pub fn fold(&self) -> Expr {
+        match (self.op, fold(&self.expr)) {
+            (Kind::Minus, Expr::Int(literal)) => Literal::<i64>::int(literal.value.wrapping_neg()),
+            (Kind::Minus, Expr::Float(literal)) => Literal::<f64>::float(-literal.value),
+            (Kind::Bang, Expr::Bool(literal)) => Literal::<bool>::bool(!literal.value),
+            (_, expr) => node::Unary::wrapped(self.op, expr, self.ty.clone()),
         }
     }
 }
 
+/// Folds `expr` if it is arithmetic, and otherwise returns it as it is.
+pub(super) fn fold(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Binary(binary) => binary.fold(),
+        Expr::Unary(unary) => unary.fold(),
+        _ => expr.clone(),
+    }
+}
+
+fn checked(left: i64, right: i64, op: Kind) -> Option<i64> {
+    match op {
+        Kind::Plus => left.checked_add(right),
+        Kind::Minus => left.checked_sub(right),
+        Kind::Star => left.checked_mul(right),
+        Kind::Slash => left.checked_div(right),
+        _ => None,
+    }
+}
+
 fn apply<T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>>(
     left: T,
     right: T,
     op: Kind,
-) -> T {
+) -> Option<T> {
     match op {
-        Kind::Plus => left + right,
-        Kind::Minus => left - right,
-        Kind::Star => left * right,
-        Kind::Slash => left / right,
-        _ => unreachable!(),
+        Kind::Plus => Some(left + right),
+        Kind::Minus => Some(left - right),
+        Kind::Star => Some(left * right),
+        Kind::Slash => Some(left / right),
+        _ => None,
     }
 }
@@ -4,4 +4,4 @@ mod typecheck;
 
 pub use symbol::Symbol;
 pub use symbol::SymbolTable;
-pub use typecheck::{resolve_types, ResolvedMetaInfo};
+pub use typecheck::resolve_types;
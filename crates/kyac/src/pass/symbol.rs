@@ -1,6 +1,7 @@
 use crate::{
     ast::{node, Decl},
     builtins,
+    token::Token,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -10,15 +11,10 @@ use std::{
 #[derive(Debug, Clone)]
 pub enum Symbol {
     Class(Rc<node::ClassDecl>),
+    Interface(Rc<node::InterfaceDecl>),
     Function(Rc<node::FuncDecl>),
-    Constant(Rc<node::ConstantDecl>),
-    Variable(Rc<node::VarDecl>),
-    Opaque(String),
-    Str,
-    Int,
-    Float,
-    Bool,
-    Void,
+    Constant,
+    Alias(Rc<node::AliasDecl>),
 }
 
 impl Symbol {
@@ -29,15 +25,30 @@ impl Symbol {
         }
     }
 
-    pub fn is_ptr(&self) -> bool {
-        matches!(self, Symbol::Class(_) | Symbol::Str)
+    pub fn interface(&self) -> Option<&Rc<node::InterfaceDecl>> {
+        match self {
+            Symbol::Interface(interface) => Some(interface),
+            _ => None,
+        }
     }
 
-    pub fn function(&self) -> &node::FuncDecl {
-        match self {
-            Symbol::Function(fun) => fun,
-            _ => panic!("called `Symbol::function()` on a non-function symbol"),
+    /// Every interface implemented by `cls`, including those declared by its superclasses.
+    pub fn interfaces<'a>(
+        cls: &'a node::ClassDecl,
+        symbols: &'a SymbolTable,
+    ) -> Vec<&'a Rc<node::InterfaceDecl>> {
+        let mut interfaces: Vec<&Rc<node::InterfaceDecl>> = vec![];
+        for cls in Self::superclasses(cls, symbols) {
+            for name in &cls.interfaces {
+                if let Some(interface) = symbols.get(&name.to_string()).and_then(Symbol::interface)
+                {
+                    if !interfaces.iter().any(|i| i.name == interface.name) {
+                        interfaces.push(interface);
+                    }
+                }
+            }
         }
+        interfaces
     }
 
     pub fn has_subclass<'a>(cls: &'a node::ClassDecl, symbols: &'a SymbolTable) -> bool {
@@ -71,54 +82,146 @@ impl Symbol {
             .collect()
     }
 
+    /// The type parameters of the class and its superclasses, from the root of the hierarchy down.
+    /// A subclass names the parameters it passes on to its parent, so each is only counted once,
+    /// and the parameters of a class come first in those of any of its subclasses.
+    pub fn type_parameters(&self, symbols: &SymbolTable) -> Vec<String> {
+        let cls = self.class().unwrap();
+        let mut params: Vec<String> = vec![];
+        for cls in Self::superclasses(cls, symbols).into_iter().rev() {
+            for tp in cls.tp.iter().flatten() {
+                let name = tp.name.to_string();
+                if !params.contains(&name) {
+                    params.push(name);
+                }
+            }
+        }
+        params
+    }
+
+    /// The dispatch table of the class: every method it has (including inherited ones), along
+    /// with the label of its most derived declaration. A method that is still abstract is
+    /// labelled with the declaration that has no body, and so cannot be called directly.
     pub fn methods(&self, symbols: &SymbolTable) -> Vec<(String, Rc<node::FuncDecl>)> {
+        self.dispatch(symbols)
+            .into_iter()
+            .map(|(label, method, _)| (label, method))
+            .collect()
+    }
+
+    /// The methods of the class that are abstract and not overridden by a body.
+    pub fn abstract_methods(&self, symbols: &SymbolTable) -> Vec<(String, Rc<node::FuncDecl>)> {
+        self.dispatch(symbols)
+            .into_iter()
+            .filter(|(_, _, active)| active.r#abstract)
+            .map(|(label, method, _)| (label, method))
+            .collect()
+    }
+
+    /// The static method `name` of the class or of its nearest superclass that declares one,
+    /// along with its label.
+    pub fn static_method(
+        &self,
+        name: &Token,
+        symbols: &SymbolTable,
+    ) -> Option<(String, Rc<node::FuncDecl>)> {
+        let cls = self.class().unwrap();
+        Self::superclasses(cls, symbols)
+            .into_iter()
+            .find_map(|cls| {
+                cls.methods
+                    .iter()
+                    .find(|m| m.r#static && m.name == *name)
+                    .map(|m| (format!("{}.{}", cls.name, m.name), Rc::clone(m)))
+            })
+    }
+
+    fn dispatch<'a>(
+        &'a self,
+        symbols: &'a SymbolTable,
+    ) -> Vec<(String, Rc<node::FuncDecl>, &'a node::FuncDecl)> {
         let cls = self.class().unwrap();
         let superclasses = Self::superclasses(cls, symbols);
         let mut used = HashSet::new();
         let mut methods = vec![];
         for c in superclasses.iter().rev() {
-            for method in &c.methods {
-                let active = superclasses
+            // Static methods are called on the class, so they have no place in its dispatch table
+            for method in c.methods.iter().filter(|m| !m.r#static) {
+                let (owner, active) = superclasses
                     .iter()
                     .find_map(|cls| {
                         cls.methods
                             .iter()
-                            .find(|m| m.name == method.name)
-                            .map(|m| format!("{}.{}", cls.name, m.name))
+                            .find(|m| !m.r#static && m.name == method.name)
+                            .map(|m| (cls, m))
                     })
                     .unwrap_or_else(|| panic!("expected to find method {}", method.name));
-                let (_, n) = active.rsplit_once('.').unwrap();
-                if !used.contains(n) {
-                    methods.push((active.clone(), Rc::clone(method)));
-                    used.insert(n.to_string());
+                let n = method.name.to_string();
+                if !used.contains(&n) {
+                    let label = format!("{}.{}", owner.name, active.name);
+                    methods.push((label, Rc::clone(method), &**active));
+                    used.insert(n);
                 }
             }
         }
         methods
     }
+}
 
-    pub fn descriptor(&self, symbols: &SymbolTable) -> (String, Vec<String>) {
-        let methods = self
-            .methods(symbols)
-            .iter()
-            .map(|(label, _)| label)
-            .cloned()
-            .collect();
-        let fields = self
-            .fields(symbols)
+crate::newtype!(SymbolTable:HashMap<String, Symbol>);
+
+impl SymbolTable {
+    /// The range of class IDs covering each class and its subclasses. IDs are assigned in a
+    /// preorder walk of the class hierarchy, so the subclasses of a class are numbered right after
+    /// it. No class has the ID 0.
+    pub fn class_ids(&self) -> HashMap<String, (i64, i64)> {
+        let mut classes: Vec<_> = self.values().filter_map(Symbol::class).collect();
+        classes.sort_by_key(|cls| cls.name.to_string());
+        let mut ids = HashMap::new();
+        let mut next = 1;
+        for root in classes.iter().filter(|cls| cls.parent.is_none()) {
+            Self::number(root, &classes, &mut next, &mut ids);
+        }
+        ids
+    }
+
+    fn number(
+        cls: &node::ClassDecl,
+        classes: &[&node::ClassDecl],
+        next: &mut i64,
+        ids: &mut HashMap<String, (i64, i64)>,
+    ) {
+        let first = *next;
+        *next += 1;
+        let subclasses = classes.iter().filter(|subclass| {
+            subclass
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.lexeme == cls.name.lexeme)
+        });
+        for subclass in subclasses {
+            Self::number(subclass, classes, next, ids);
+        }
+        ids.insert(cls.name.to_string(), (first, *next - 1));
+    }
+
+    /// Every interface method in the program, as `(interface, method)` pairs. The position of a
+    /// pair is the slot that holds its implementation in each class's dispatch table.
+    pub fn interface_methods(&self) -> Vec<(String, String)> {
+        let mut interfaces: Vec<_> = self.values().filter_map(Symbol::interface).collect();
+        interfaces.sort_by_key(|interface| interface.name.to_string());
+        interfaces
             .iter()
-            .map(|f| match f.ty.base.lexeme.unwrap() {
-                "int" | "float" | "bool" => 'i',
-                "void" => panic!("class cannot contain void field"),
-                _ => 'p',
+            .flat_map(|interface| {
+                interface
+                    .methods
+                    .iter()
+                    .map(|method| (interface.name.to_string(), method.name.to_string()))
             })
-            .collect();
-        (fields, methods)
+            .collect()
     }
 }
 
-crate::newtype!(SymbolTable:HashMap<String, Symbol>);
-
 impl From<&Vec<Decl>> for SymbolTable {
     fn from(nodes: &Vec<Decl>) -> Self {
         let mut table: Self = Self(
@@ -143,6 +246,8 @@ impl ToTuple for Decl {
             Decl::Function(fun) => fun.to_tuple(),
             Decl::Constant(c) => c.to_tuple(),
             Decl::Class(cls) => cls.to_tuple(),
+            Decl::Interface(interface) => interface.to_tuple(),
+            Decl::Alias(alias) => alias.to_tuple(),
         }
     }
 }
@@ -155,7 +260,7 @@ impl ToTuple for Rc<node::FuncDecl> {
 
 impl ToTuple for Rc<node::ConstantDecl> {
     fn to_tuple(&self) -> (String, Symbol) {
-        (self.name.to_string(), Symbol::Constant(Rc::clone(self)))
+        (self.name.to_string(), Symbol::Constant)
     }
 }
 
@@ -164,3 +269,15 @@ impl ToTuple for Rc<node::ClassDecl> {
         (self.name.to_string(), Symbol::Class(Rc::clone(self)))
     }
 }
+
+impl ToTuple for Rc<node::InterfaceDecl> {
+    fn to_tuple(&self) -> (String, Symbol) {
+        (self.name.to_string(), Symbol::Interface(Rc::clone(self)))
+    }
+}
+
+impl ToTuple for Rc<node::AliasDecl> {
+    fn to_tuple(&self) -> (String, Symbol) {
+        (self.name.to_string(), Symbol::Alias(Rc::clone(self)))
+    }
+}
@@ -10,10 +10,15 @@ pub enum Kind {
     RightBrace,
     LeftBracket,
     RightBracket,
+    /// The `{` opening an expression interpolated into a string
+    InterpolationStart,
+    /// The `}` closing an expression interpolated into a string
+    InterpolationEnd,
     Semicolon,
     Colon,
     Comma,
     Dot,
+    Question,
     Plus,
     Minus,
     Star,
@@ -27,6 +32,8 @@ pub enum Kind {
     Less,
     LessEqual,
     Let,
+    Var,
+    Mut,
     Const,
     Fun,
     Return,
@@ -37,7 +44,20 @@ pub enum Kind {
     For,
     In,
     Class,
+    Struct,
+    Abstract,
+    Static,
+    Pub,
+    Protected,
+    Private,
+    Interface,
+    Implements,
+    Type,
     Init,
+    Super,
+    Is,
+    As,
+    Nil,
     Error,
     Eof,
 }
@@ -55,25 +75,41 @@ impl fmt::Display for Kind {
             Kind::LessEqual => write!(f, "<="),
             Kind::LeftParen => write!(f, "("),
             Kind::RightParen => write!(f, ")"),
-            Kind::LeftBrace => write!(f, "{{"),
-            Kind::RightBrace => write!(f, "}}"),
+            Kind::LeftBrace | Kind::InterpolationStart => write!(f, "{{"),
+            Kind::RightBrace | Kind::InterpolationEnd => write!(f, "}}"),
             Kind::LeftBracket => write!(f, "["),
             Kind::RightBracket => write!(f, "]"),
             Kind::Semicolon => write!(f, ";"),
             Kind::Colon => write!(f, ":"),
             Kind::Comma => write!(f, ","),
             Kind::Dot => write!(f, "."),
+            Kind::Question => write!(f, "?"),
             Kind::Plus => write!(f, "add"),
             Kind::Minus => write!(f, "subtract"),
             Kind::Star => write!(f, "multiply"),
             Kind::Slash => write!(f, "divide"),
             Kind::Let => write!(f, "let"),
+            Kind::Var => write!(f, "var"),
+            Kind::Mut => write!(f, "mut"),
             Kind::Const => write!(f, "const"),
             Kind::Fun => write!(f, "fun"),
             Kind::Extern => write!(f, "extern"),
             Kind::Return => write!(f, "return"),
             Kind::Class => write!(f, "class"),
+            Kind::Struct => write!(f, "struct"),
+            Kind::Abstract => write!(f, "abstract"),
+            Kind::Static => write!(f, "static"),
+            Kind::Pub => write!(f, "pub"),
+            Kind::Protected => write!(f, "protected"),
+            Kind::Private => write!(f, "private"),
+            Kind::Interface => write!(f, "interface"),
+            Kind::Implements => write!(f, "implements"),
+            Kind::Type => write!(f, "type"),
             Kind::Init => write!(f, "init"),
+            Kind::Super => write!(f, "super"),
+            Kind::Is => write!(f, "is"),
+            Kind::As => write!(f, "as"),
+            Kind::Nil => write!(f, "nil"),
             Kind::If => write!(f, "if"),
             Kind::Else => write!(f, "else"),
             Kind::For => write!(f, "for"),
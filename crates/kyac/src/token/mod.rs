@@ -135,12 +135,14 @@ impl<'a> Lexer<'a> {
                     ',' => Token::new(Kind::Comma, None, self.span),
                     ':' => Token::new(Kind::Colon, None, self.span),
                     '.' => Token::new(Kind::Dot, None, self.span),
+                    '?' => Token::new(Kind::Question, None, self.span),
                     '{' => Token::new(Kind::LeftBrace, None, self.span),
                     '}' => Token::new(Kind::RightBrace, None, self.span),
                     '[' => Token::new(Kind::LeftBracket, None, self.span),
                     ']' => Token::new(Kind::RightBracket, None, self.span),
                     // Types
                     '"' => self.string(),
+                    '\'' => self.character(),
                     '0'..='9' => self.number(),
                     // Math
                     '+' => Token::new(Kind::Plus, None, self.span),
@@ -159,14 +161,11 @@ impl<'a> Lexer<'a> {
                         return;
                     }
                     c => {
-                        let error = PreciseError::new(
-                            self.source,
+                        self.error(
                             self.span,
                             format!("unexpected character `{c}`"),
                             "not a token".into(),
                         );
-                        println!("{error}");
-                        self.errors.push(error);
                         Token::new(Kind::Error, None, self.span)
                     }
                 }
@@ -176,57 +175,306 @@ impl<'a> Lexer<'a> {
         self.tokens.push_back(token);
     }
 
+    fn error(&mut self, span: Span, heading: String, text: String) {
+        let error = PreciseError::new(self.source, span, heading, text);
+        println!("{error}");
+        self.errors.push(error);
+    }
+
     fn peek(&self) -> Option<char> {
         self.source.chars.get(self.current).copied()
     }
 
+    /// Lexes a string literal, processing its escape sequences. The lexeme of the token keeps the
+    /// delimiting quotes.
+    ///
+    /// Each `{expr}` interpolated into the string splits it into segments: the segment before it
+    /// is pushed as its own literal, followed by the tokens of `expr` between `InterpolationStart`
+    /// and `InterpolationEnd`. The last segment is returned, so a string always ends with a literal.
     fn string(&mut self) -> Token {
-        self.start = self.current;
         let oquote = self.span;
-        let mut peeked = self.peek();
-        while !self.eof() && peeked.unwrap() != '"' {
-            self.consume();
-            peeked = self.peek();
+        let mut segment = Span::new(self.span.line, self.span.column, 1);
+        let mut cooked = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    self.error(
+                        oquote,
+                        "unterminated string".into(),
+                        "opening quote here".into(),
+                    );
+                    return Token::new(Kind::Error, None, self.span);
+                }
+                Some('"') => {
+                    self.consume();
+                    break;
+                }
+                Some('\\') => {
+                    if let Some(c) = self.escape() {
+                        cooked.push(c);
+                    }
+                }
+                Some('{') => {
+                    self.consume();
+                    segment.length = self.span.column - segment.column + 1;
+                    let lexeme = format!("\"{cooked}\"").leak();
+                    self.tokens
+                        .push_back(Token::new(Kind::Literal, Some(lexeme), segment));
+                    let open = Span::new(self.span.line, self.span.column, 1);
+                    self.tokens
+                        .push_back(Token::new(Kind::InterpolationStart, None, open));
+                    let errors = self.errors.len();
+                    if !self.interpolation() {
+                        // A string opened inside the expression has already been reported
+                        if self.errors.len() == errors {
+                            self.error(
+                                oquote,
+                                "unterminated string".into(),
+                                "opening quote here".into(),
+                            );
+                        }
+                        return Token::new(Kind::Error, None, self.span);
+                    }
+                    cooked.clear();
+                    segment = Span::new(self.span.line, self.span.column, 1);
+                }
+                Some(c) => {
+                    self.consume();
+                    cooked.push(c);
+                }
+            }
         }
-        self.consume(); // don't forget the closing quote
-        self.span.length = self.current - self.start + 1;
-        if self.eof() {
-            let error = PreciseError::new(
-                self.source,
-                oquote,
-                "unterminated string".into(),
-                "opening quote here".into(),
+        segment.length = self.span.column - segment.column + 1;
+        let lexeme = format!("\"{cooked}\"").leak();
+        Token::new(Kind::Literal, Some(lexeme), segment)
+    }
+
+    /// Lexes a character literal, which holds exactly one character or escape sequence. The lexeme
+    /// of the token keeps the delimiting quotes.
+    fn character(&mut self) -> Token {
+        let oquote = self.span;
+        let mut cooked = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => {
+                    self.error(
+                        oquote,
+                        "unterminated character literal".into(),
+                        "opening quote here".into(),
+                    );
+                    return Token::new(Kind::Error, None, self.span);
+                }
+                Some('\'') => {
+                    self.consume();
+                    break;
+                }
+                Some('\\') => {
+                    // An invalid escape has been reported, so it stands in for a character
+                    cooked.push(self.escape().unwrap_or('\0'));
+                }
+                Some(c) => {
+                    self.consume();
+                    cooked.push(c);
+                }
+            }
+        }
+        let span = Span::new(
+            oquote.line,
+            oquote.column,
+            self.span.column - oquote.column + 1,
+        );
+        if cooked.chars().count() != 1 {
+            let heading = if cooked.is_empty() {
+                "empty character literal"
+            } else {
+                "character literal may only contain one character"
+            };
+            self.error(
+                span,
+                heading.into(),
+                "use double quotes for a string".into(),
             );
-            println!("{error}");
-            self.errors.push(error);
-            return Token::new(Kind::Error, None, self.span);
+            return Token::new(Kind::Error, None, span);
         }
-        let lexeme = self.lexeme(self.start - 1, self.current);
-        self.adjusted(|lexer| Token::new(Kind::Literal, Some(lexeme.leak()), lexer.span))
+        let lexeme = format!("'{cooked}'").leak();
+        Token::new(Kind::Literal, Some(lexeme), span)
     }
 
-    fn number(&mut self) -> Token {
-        self.start = self.current - 1;
-        let mut peeked = self.peek();
-        while !self.eof() && Self::num(peeked.unwrap()) {
-            self.consume();
-            peeked = self.peek();
+    /// Lexes the tokens of an expression interpolated into a string, up to and including the
+    /// closing `}`. Returns `false` if the end of the file is reached first.
+    fn interpolation(&mut self) -> bool {
+        let mut depth = 0usize;
+        loop {
+            let count = self.tokens.len();
+            self.advance();
+            if self.tokens.len() == count {
+                // a comment
+                continue;
+            }
+            match self.tokens.back().unwrap().kind {
+                Kind::LeftBrace => depth += 1,
+                Kind::RightBrace if depth == 0 => {
+                    let close = self.tokens.pop_back().unwrap();
+                    self.tokens
+                        .push_back(Token::new(Kind::InterpolationEnd, None, close.span));
+                    return true;
+                }
+                Kind::RightBrace => depth -= 1,
+                Kind::Eof => {
+                    self.tokens.pop_back();
+                    return false;
+                }
+                _ => {}
+            }
         }
+    }
 
-        if !self.eof() && self.peek().unwrap() == '.' {
+    /// Lexes an escape sequence starting with `\`, returning the character it stands for.
+    fn escape(&mut self) -> Option<char> {
+        self.consume();
+        let mut span = Span::new(self.span.line, self.span.column, 2);
+        let c = self.peek()?;
+        self.consume();
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '"' | '\'' | '\\' | '{' | '}' => Some(c),
+            'u' => {
+                let mut digits = String::new();
+                let braced = self.peek() == Some('{');
+                if braced {
+                    self.consume();
+                    while let Some(c) = self.peek().filter(char::is_ascii_hexdigit) {
+                        self.consume();
+                        digits.push(c);
+                    }
+                }
+                let closed = braced && self.peek() == Some('}');
+                if closed {
+                    self.consume();
+                }
+                span.length = self.span.column - span.column + 1;
+                let c = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| closed && digits.len() <= 6)
+                    .and_then(char::from_u32);
+                if c.is_none() {
+                    self.error(
+                        span,
+                        "invalid unicode escape".into(),
+                        "expected `\\u{...}` with 1 to 6 hex digits naming a character".into(),
+                    );
+                }
+                c
+            }
+            c => {
+                self.error(
+                    span,
+                    format!("unknown escape sequence `\\{c}`"),
+                    "not a valid escape".into(),
+                );
+                None
+            }
+        }
+    }
+
+    /// Lexes a number: an integer with an optional `0x`, `0o` or `0b` radix prefix, or a decimal
+    /// with an optional fractional part and exponent. Digits may be separated by `_`.
+    fn number(&mut self) -> Token {
+        self.start = self.current - 1;
+        let radix = match (self.source.chars[self.start], self.peek()) {
+            ('0', Some('x' | 'X')) => Some(("hexadecimal", 16)),
+            ('0', Some('o' | 'O')) => Some(("octal", 8)),
+            ('0', Some('b' | 'B')) => Some(("binary", 2)),
+            _ => None,
+        };
+        let mut error = None;
+        if let Some((name, radix)) = radix {
             self.consume();
-            peeked = self.peek();
-            while !self.eof() && Self::num(peeked.unwrap()) {
+            // Take any alphanumeric characters, so that an invalid digit is part of the literal
+            let digits = self.digits(char::is_ascii_alphanumeric);
+            if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+                error = Some((
+                    format!("invalid digit `{c}` in {name} literal"),
+                    format!(
+                        "{name} literals may only contain the digits {}",
+                        Self::radix_digits(radix)
+                    ),
+                ));
+            } else if digits.is_empty() {
+                error = Some((
+                    format!("missing digits in {name} literal"),
+                    "expected at least one digit after the prefix".into(),
+                ));
+            }
+        } else {
+            self.digits(char::is_ascii_digit);
+            // A number after a `.` is the index of a tuple element, so `pair.0.1` reads two
+            // elements rather than a float
+            let element = self
+                .tokens
+                .back()
+                .is_some_and(|token| token.kind == Kind::Dot);
+            if self.peek() == Some('.') && !element {
                 self.consume();
-                peeked = self.peek();
+                self.digits(char::is_ascii_digit);
+            }
+            if !element && matches!(self.peek(), Some('e' | 'E')) {
+                self.consume();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.consume();
+                }
+                if self.digits(char::is_ascii_digit).is_empty() {
+                    error = Some((
+                        "missing digits in exponent".into(),
+                        "expected at least one digit after `e`".into(),
+                    ));
+                }
+            }
+            let suffix = self.digits(char::is_ascii_alphanumeric);
+            if !suffix.is_empty() {
+                error = Some((
+                    format!("invalid suffix `{suffix}` on number"),
+                    "numbers may not be followed by letters".into(),
+                ));
             }
         }
 
         let lexeme = self.lexeme(self.start, self.current);
         self.span.length = self.current - self.start;
+        if let Some((heading, text)) = error {
+            let span = Span::new(
+                self.span.line,
+                self.span.column - self.span.length + 1,
+                self.span.length,
+            );
+            self.error(span, heading, text);
+            return self.adjusted(|lexer| Token::new(Kind::Error, None, lexer.span));
+        }
         self.adjusted(|lexer| Token::new(Kind::Literal, Some(lexeme.leak()), lexer.span))
     }
 
+    /// Consumes the characters matching `digit` or `_`, returning the digits consumed.
+    fn digits(&mut self, digit: fn(&char) -> bool) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(|c| digit(c) || *c == '_') {
+            self.consume();
+            if c != '_' {
+                digits.push(c);
+            }
+        }
+        digits
+    }
+
+    fn radix_digits(radix: u32) -> &'static str {
+        match radix {
+            2 => "0 and 1",
+            8 => "0 to 7",
+            _ => "0 to 9 and a to f",
+        }
+    }
+
     fn identifier(&mut self) -> Token {
         self.start = self.current - 1;
         let mut peeked = self.peek();
@@ -241,13 +489,28 @@ impl<'a> Lexer<'a> {
         self.span.length = self.current - self.start;
         self.adjusted(|lexer| match lexeme.as_str() {
             "let" => Token::new(Kind::Let, None, lexer.span),
+            "var" => Token::new(Kind::Var, None, lexer.span),
+            "mut" => Token::new(Kind::Mut, None, lexer.span),
             "const" => Token::new(Kind::Const, None, lexer.span),
             "fun" => Token::new(Kind::Fun, None, lexer.span),
             "true" | "false" => Token::new(Kind::Literal, Some(lexeme.leak()), lexer.span),
             "return" => Token::new(Kind::Return, None, lexer.span),
             "extern" => Token::new(Kind::Extern, None, lexer.span),
             "class" => Token::new(Kind::Class, None, lexer.span),
+            "struct" => Token::new(Kind::Struct, None, lexer.span),
+            "abstract" => Token::new(Kind::Abstract, None, lexer.span),
+            "static" => Token::new(Kind::Static, None, lexer.span),
+            "pub" => Token::new(Kind::Pub, None, lexer.span),
+            "protected" => Token::new(Kind::Protected, None, lexer.span),
+            "private" => Token::new(Kind::Private, None, lexer.span),
+            "interface" => Token::new(Kind::Interface, None, lexer.span),
+            "implements" => Token::new(Kind::Implements, None, lexer.span),
+            "type" => Token::new(Kind::Type, None, lexer.span),
             "init" => Token::new(Kind::Init, None, lexer.span),
+            "super" => Token::new(Kind::Super, None, lexer.span),
+            "is" => Token::new(Kind::Is, None, lexer.span),
+            "as" => Token::new(Kind::As, None, lexer.span),
+            "nil" => Token::new(Kind::Nil, None, lexer.span),
             "if" => Token::new(Kind::If, None, lexer.span),
             "else" => Token::new(Kind::Else, None, lexer.span),
             "while" => Token::new(Kind::While, None, lexer.span),
@@ -284,12 +547,9 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Whether `c` can continue an identifier, which may contain (but not start with) digits.
     fn ident(c: char) -> bool {
-        matches!(c, 'a'..='z' | 'A'..='Z' | '_')
-    }
-
-    fn num(c: char) -> bool {
-        c.is_ascii_digit()
+        matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_')
     }
 
     fn whitespace(c: char) -> bool {
@@ -351,8 +611,16 @@ assert_tokens! {
     "test-cases/empty.kya" => empty / true,
     "test-cases/access.kya" => access / true,
     "test-cases/mixed.kya" => mixed / true,
+    "test-cases/strings.kya" => strings / true,
+    "test-cases/numbers.kya" => numbers / true,
+    "test-cases/chars.kya" => chars / true,
+    "test-cases/tuples.kya" => tuples / true,
+    "test-cases/optionals.kya" => optionals / true,
 
     "test-cases/tokens/varied.kya" => varied / false,
     "test-cases/tokens/loooong.kya" => loooong / false,
-    "test-cases/tokens/unterm.kya" => unterm / false
+    "test-cases/tokens/unterm.kya" => unterm / false,
+    "test-cases/tokens/escapes.kya" => escapes / false,
+    "test-cases/tokens/numbers.kya" => malformed_numbers / false,
+    "test-cases/tokens/chars.kya" => malformed_chars / false
 }
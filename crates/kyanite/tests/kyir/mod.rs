@@ -142,6 +142,13 @@ fn nested_early_return() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn return_then_loop() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/return-then-loop.kya")?;
+    assert_eq!(res.output, "1\n2\n6\n");
+    Ok(())
+}
+
 #[test]
 fn multi_depth_functions() -> Result<(), Box<dyn std::error::Error>> {
     let res = run("kyir/multi-depth-functions.kya")?;
@@ -255,6 +262,13 @@ fn gc_shared_child_field() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn gc_shared_string() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/gc-shared-string.kya")?;
+    assert_eq!(res.output, "shared!\nshared!\nshared!\n");
+    Ok(())
+}
+
 #[test]
 fn basic_methods() -> Result<(), Box<dyn std::error::Error>> {
     let res = run("kyir/basic-methods.kya")?;
@@ -344,3 +358,187 @@ fn basic_generics() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(res.output, "42\n42\n42\n");
     Ok(())
 }
+
+#[test]
+fn generic_layout() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/generic-layout.kya")?;
+    assert_eq!(res.output, "7\n3\n11\n");
+    Ok(())
+}
+
+#[test]
+fn generic_collection() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/generic-collection.kya")?;
+    assert_eq!(res.output, "7\n3\n7\n3\n");
+    Ok(())
+}
+
+#[test]
+fn interfaces() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/interfaces.kya")?;
+    assert_eq!(res.output, "square\n9\nrect\n10\nsquare\n25\n41\n");
+    Ok(())
+}
+
+#[test]
+fn super_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/super-calls.kya")?;
+    assert_eq!(
+        res.output,
+        "woof\ni'm a golden retriever\nhello\nrex\nwoof\ni'm a golden retriever\nwoof\ni'm a golden retriever\n11\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn type_tests() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/type-tests.kya")?;
+    assert_eq!(
+        res.output,
+        "meow\n9\nfalse\nwoof\nfetching\nfalse\nwoof\nfetching\ntrue\nfetching\n3\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn closures() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/closures.kya")?;
+    assert_eq!(res.output, "8\n15\n4\n7\nnested\ndone\n");
+    Ok(())
+}
+
+#[test]
+fn strings() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/strings.kya")?;
+    assert_eq!(
+        res.output,
+        "Hello, world!\n13\nw\nworld\ntrue\nfalse\ntrue\ntrue\nHi, Ada (36)\nababab\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn interpolation() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/interpolation.kya")?;
+    assert_eq!(
+        res.output,
+        "Hello, Ada! You have 4 new messages.\np = (4, -2), above the axis: false\nAda\ntab:\t\"quoted\" \\ {braces}\ntwo\nlines\ncafé\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn numbers() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/numbers.kya")?;
+    assert_eq!(
+        res.output,
+        "280\n1000000\n3735928559\n-9223372036854775808\n-84\n7\n9223372036854775807\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn sized_ints() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/sized-ints.kya")?;
+    assert_eq!(
+        res.output,
+        "4\n-128\n44\n65535\n9223372036854775807\ntrue\n-9\n7\n44\n-3\n-1000\npx\n4000000000\n5\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn float_arithmetic() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/float-arithmetic.kya")?;
+    assert_eq!(
+        res.output,
+        "-25\ntrue\ntrue\n50\nfalse\ntrue\n125\nfalse\ntrue\n200\nfalse\ntrue\n-5\nfalse\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn chars() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/chars.kya")?;
+    assert_eq!(res.output, "c\né\n233\nc\ntrue\nfalse\n( x 3\nx\ny\nz\n");
+    Ok(())
+}
+
+#[test]
+fn tuples() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/tuples.kya")?;
+    assert_eq!(
+        res.output,
+        "3\n2\napples!\n6\ntrue\napples and pears: 7\n32\n13\n1\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn optionals() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/optionals.kya")?;
+    assert_eq!(res.output, "0\n15\n3\ntrue\n9 is missing\nkyanite!\n");
+    Ok(())
+}
+
+#[test]
+fn results() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/results.kya")?;
+    assert_eq!(
+        res.output,
+        "true\n123\n42 360\n'x' is not a digit\n'?' is not a digit\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn operators() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/operators.kya")?;
+    assert_eq!(res.output, "7\n10\ntrue\ntrue\ntrue\ntrue\n");
+    Ok(())
+}
+
+#[test]
+fn init() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/init.kya")?;
+    assert_eq!(res.output, "0\nfalse\ntrue\n20\ncy\nfalse\n");
+    Ok(())
+}
+
+#[test]
+fn static_methods() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/static.kya")?;
+    assert_eq!(res.output, "0\n42\n3\n");
+    Ok(())
+}
+
+#[test]
+fn visibility() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/visibility.kya")?;
+    assert_eq!(res.output, "2\ndouble: 4\n");
+    Ok(())
+}
+
+#[test]
+fn structs() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/structs.kya")?;
+    assert_eq!(res.output, "1\n12\n2\n8\n41\n40\n10\nhero\nother\n7\n");
+    Ok(())
+}
+
+#[test]
+fn aliases() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/aliases.kya")?;
+    assert_eq!(res.output, "7\n3\n1\n");
+    Ok(())
+}
+
+#[test]
+fn iterators() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("kyir/iterators.kya")?;
+    assert_eq!(
+        res.output,
+        "10\n10\n7\n4\n1\n1\n3\n5\n7\nc\na\nf\né\n!\n5\n3\n2\n1\n10\na\nb\n"
+    );
+    Ok(())
+}
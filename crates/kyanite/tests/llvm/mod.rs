@@ -1,4 +1,4 @@
-use kyac::{Backend, Output, Source};
+use kyac::{Backend, Output, PipelineError, Source};
 use kyanite::subprocess::ProcessResult;
 
 fn run(name: &str) -> Result<ProcessResult, Box<dyn std::error::Error>> {
@@ -51,3 +51,27 @@ fn nested() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(res.output, "100\n500\n80\nHello\nWorld\n!\nWorld\n");
     Ok(())
 }
+
+#[test]
+fn control_flow() -> Result<(), Box<dyn std::error::Error>> {
+    let res = run("llvm/control-flow.kya")?;
+    assert_eq!(res.code, 0);
+    assert_eq!(res.output, "3\n10\n-1\n0\n1\n10\n1\n");
+    Ok(())
+}
+
+#[test]
+fn interfaces() -> Result<(), Box<dyn std::error::Error>> {
+    let source = Source::new(super::path("kyir/interfaces.kya")?)?;
+    let res = kyac::compile(&source, &Backend::Llvm);
+    assert!(matches!(res, Err(PipelineError::IrError(_))));
+    Ok(())
+}
+
+#[test]
+fn type_tests() -> Result<(), Box<dyn std::error::Error>> {
+    let source = Source::new(super::path("kyir/type-tests.kya")?)?;
+    let res = kyac::compile(&source, &Backend::Llvm);
+    assert!(matches!(res, Err(PipelineError::IrError(_))));
+    Ok(())
+}
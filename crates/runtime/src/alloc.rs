@@ -1,16 +1,76 @@
 use bumpalo::Bump;
-use std::{alloc::Layout, collections::HashMap, ffi::CStr, ptr::NonNull, sync::Mutex};
+use std::{
+    alloc::Layout,
+    collections::{HashMap, HashSet},
+    ffi::CStr,
+    ptr::NonNull,
+    sync::Mutex,
+};
 
 /// The maximum number of bytes that can be allocated before
 /// running the garbage collector.
 const LIMIT: usize = 4_000_000;
-/// The metadata fields count for classes.
-pub const CLASS_METADATA_FIELDS: usize = 2;
+/// The metadata fields count for classes: the descriptor, the method array, the class ID and the
+/// type layout.
+pub const CLASS_METADATA_FIELDS: usize = 4;
+/// The index of the metadata field holding the type layout of an instance of a generic class:
+/// bit `k` is set if its `k`th type argument holds a pointer.
+pub const LAYOUT_FIELD: usize = 3;
 /// The metadata fields count for arrays.
 pub const ARRAY_METADATA_FIELDS: usize = 1;
 
+/// The size in bytes of a field with the descriptor character `c`: `b`, `h` and `w` are 1, 2 and 4
+/// byte numbers, while pointers (`p`), other values (`i`) and fields whose type is a type parameter
+/// (`A` to `Z`) take a whole word.
+fn field_size(c: u8) -> usize {
+    match c {
+        b'b' => 1,
+        b'h' => 2,
+        b'w' => 4,
+        _ => 8,
+    }
+}
+
+/// The offset in bytes of each field of a class with `descriptor`, from the start of an instance.
+/// Fields follow the metadata in order, each aligned to its own size. A struct embedded in a class
+/// has a character for each of its own fields, so they are laid out (and traced) like any other.
+#[must_use]
+pub fn field_offsets(descriptor: &str) -> Vec<usize> {
+    let mut offset = CLASS_METADATA_FIELDS * 8;
+    descriptor
+        .bytes()
+        .map(|c| {
+            let size = field_size(c);
+            let field = offset.next_multiple_of(size);
+            offset = field + size;
+            field
+        })
+        .collect()
+}
+
+/// Whether a field with the descriptor character `c` holds a pointer. A field whose type is the
+/// `k`th type parameter of its class is written as the `k`th capital letter, and holds a pointer if
+/// bit `k` of the instance's type `layout` is set.
+fn is_pointer(c: u8, layout: u64) -> bool {
+    match c {
+        b'p' => true,
+        b'A'..=b'Z' => layout & (1 << (c - b'A')) != 0,
+        _ => false,
+    }
+}
+
+/// The number of words in an instance of a class with `descriptor`, including its metadata.
+fn class_words(descriptor: &str) -> usize {
+    let end = field_offsets(descriptor)
+        .last()
+        .map_or(CLASS_METADATA_FIELDS * 8, |offset| {
+            offset + field_size(*descriptor.as_bytes().last().unwrap())
+        });
+    end.div_ceil(8)
+}
+
 lazy_static::lazy_static! {
-    static ref GLOBAL: Mutex<Allocator> = Mutex::new(Allocator::new());
+    pub(crate) static ref GLOBAL: Mutex<Allocator> = Mutex::new(Allocator::new());
 }
 
 #[derive(Debug)]
@@ -18,6 +78,8 @@ pub struct Allocator {
     /// from-space
     current: Bump,
     allocations: Mutex<Vec<*const u8>>,
+    /// The allocations that hold strings rather than classes or arrays
+    strings: Mutex<HashSet<*const u8>>,
     sp: Mutex<*const u8>,
 }
 
@@ -35,6 +97,7 @@ impl Allocator {
         Self {
             current: init(),
             allocations: Mutex::new(Vec::new()),
+            strings: Mutex::new(HashSet::new()),
             sp: Mutex::new(std::ptr::null()),
         }
     }
@@ -78,10 +141,48 @@ impl Allocator {
         }
     }
 
+    /// Allocates a nul-terminated copy of `bytes`. Strings have no descriptor, so they are
+    /// remembered separately from the other allocations in order for the garbage collector to
+    /// tell them apart.
+    pub fn alloc_str(
+        &mut self,
+        bytes: &[u8],
+        frame: FrameInfo,
+        tries: usize,
+    ) -> Result<*const u8, &'static str> {
+        if std::env::var("KYANITE_GC_ALWAYS").is_ok() {
+            self.gc(&frame);
+        }
+        if tries < 2 {
+            let space = self
+                .current
+                .try_alloc_layout(Layout::array::<u64>(str_words(bytes.len())).unwrap());
+            if let Ok(ptr) = space {
+                let dst = ptr.as_ptr();
+                unsafe {
+                    std::ptr::copy(bytes.as_ptr(), dst, bytes.len());
+                    std::ptr::write(dst.add(bytes.len()), 0);
+                }
+                self.allocations.lock().unwrap().push(dst);
+                self.strings.lock().unwrap().insert(dst);
+                Ok(dst.cast_const())
+            } else {
+                self.gc(&frame);
+                self.alloc_str(bytes, frame, tries + 1)
+            }
+        } else {
+            Err("runtime: alloc: failed to allocate memory")
+        }
+    }
+
     /// A garbage collector using breadth-first copying which traverses the currently reachable stack
     /// and forwards all valid classes it finds from `self.current` (from-space) to a new region of memory
     /// using the `Bump` allocator (to-space).
     pub fn gc(&mut self, frame: &FrameInfo) {
+        // Code compiled without a frame to scan (by the LLVM backend) never collects
+        if frame.ptr.is_null() {
+            return;
+        }
         let fp = unsafe { frame.ptr.sub(frame.size.abs().try_into().unwrap()) };
         let sp = *self.sp.lock().unwrap();
         let reachable = self.reachable(fp, sp);
@@ -92,9 +193,22 @@ impl Allocator {
         ));
         let mut scratch = init();
         let mut allocations: Vec<*const u8> = Vec::new();
+        let mut strings: HashSet<*const u8> = HashSet::new();
         let mut forwarded: HashMap<*const u8, *const u8> = HashMap::new();
         let mut children: HashMap<_, Vec<*mut u8>> = HashMap::new();
         for &(loc, class) in &reachable {
+            if self.is_string(class) {
+                let forwarded = *forwarded.entry(class).or_insert_with(|| {
+                    Self::copy_string(class, &scratch, &mut allocations, &mut strings)
+                });
+                log(&format!(
+                    "runtime: gc: stack({loc:?}): forwarding string {class:?} to {forwarded:?}"
+                ));
+                unsafe {
+                    std::ptr::write::<u64>(loc.cast_mut().cast(), forwarded as u64);
+                }
+                continue;
+            }
             let descriptor = unsafe { read_string(class).0 };
             if descriptor.parse::<usize>().is_ok() {
                 continue;
@@ -103,13 +217,15 @@ impl Allocator {
                 log(&format!(
                     "runtime: gc: stack({loc:?}): (descriptor: {descriptor}), forwarding {class:?}"
                 ));
-                let count = descriptor.len() + CLASS_METADATA_FIELDS;
+                let count = class_words(&descriptor);
                 let new_region = scratch.alloc_layout(Layout::array::<u64>(count).unwrap());
                 allocations.push(new_region.as_ptr().cast());
                 self.copy_fields(
                     ClassMetadata::new(descriptor, count, class, new_region),
                     &scratch,         // to-space
                     &mut allocations, // the new(to-space) list of allocations
+                    &mut strings,     // the new(to-space) set of strings
+                    &mut forwarded,   // the strings forwarded so far
                     &mut children,    // the current list of non-forwarded child fields
                 );
                 forwarded.insert(class, new_region.as_ptr());
@@ -129,6 +245,7 @@ impl Allocator {
         log(&format!("runtime: gc: allocations: {allocations:#?}"));
         std::mem::swap(&mut self.current, &mut scratch);
         self.allocations = Mutex::new(allocations);
+        self.strings = Mutex::new(strings);
         scratch.reset();
     }
 
@@ -142,8 +259,12 @@ impl Allocator {
                 (src <= sp).then_some(src)
             })
             .filter(|src| {
-                let cls = unsafe { std::ptr::read(src.cast()) };
+                let cls: *const u8 = unsafe { std::ptr::read(src.cast()) };
                 log(&format!("runtime: gc: scanning {src:?} -> {}", cls as u64));
+                // A variable holding `nil` points at nothing
+                if cls.is_null() {
+                    return false;
+                }
                 let forward = {
                     let allocations = self.allocations.lock().unwrap();
                     allocations.contains(&cls)
@@ -164,35 +285,52 @@ impl Allocator {
         }: ClassMetadata,
         scratch: &Bump,
         allocations: &mut Vec<*const u8>,
+        strings: &mut HashSet<*const u8>,
+        forwarded: &mut HashMap<*const u8, *const u8>,
         children: &mut HashMap<u64, Vec<*mut u8>>,
     ) {
-        for (n, offset) in (0..count).map(|i| i * 8).enumerate() {
+        let layout: u64 = unsafe { std::ptr::read(class.add(LAYOUT_FIELD * 8).cast()) };
+        let pointers: Vec<_> = descriptor
+            .bytes()
+            .zip(field_offsets(&descriptor))
+            .filter_map(|(c, offset)| is_pointer(c, layout).then_some(offset))
+            .collect();
+        for offset in (0..count).map(|i| i * 8) {
             unsafe {
                 let current_value_ptr = class.add(offset);
                 let current_value: u64 = std::ptr::read(current_value_ptr.cast());
-                let array = {
+                let pointer = pointers.contains(&offset);
+                // Only a pointer field can hold a string or array; any other word that happens to
+                // equal the address of one is plain data and must be copied unchanged
+                let array = pointer.then(|| {
                     let allocations = self.allocations.lock().unwrap();
                     allocations
                         .iter()
                         .find(|&&ptr| ptr == current_value as *const u8)
                         .copied()
-                };
-                if let Some(ptr) = array {
-                    Self::copy_array(ptr, current_value_ptr, scratch, allocations);
+                });
+                if let Some(ptr) = array.flatten() {
+                    if self.is_string(ptr) {
+                        // A string referenced from several fields is only copied once
+                        let new_str = *forwarded.entry(ptr).or_insert_with(|| {
+                            Self::copy_string(ptr, scratch, allocations, strings)
+                        });
+                        std::ptr::write::<*const u8>(current_value_ptr.cast_mut().cast(), new_str);
+                    } else {
+                        Self::copy_array(ptr, current_value_ptr, scratch, allocations);
+                    }
                 }
                 let current_value: u64 = std::ptr::read(current_value_ptr.cast());
                 let new_value_ptr = new_region.as_ptr().add(offset);
                 log(&format!("runtime: gc: class[{offset}]: copying {current_value} from {current_value_ptr:?} to {new_value_ptr:?}"));
                 std::ptr::copy::<u64>(current_value_ptr.cast(), new_value_ptr.cast(), 1);
-                if n > 1 {
-                    let pointer = descriptor.as_bytes()[n - CLASS_METADATA_FIELDS] == b'p';
-                    if pointer {
-                        // we need to move *into* new_value_ptr the forwarded ptr for current_value
-                        children
-                            .entry(current_value)
-                            .or_default()
-                            .push(new_value_ptr);
-                    }
+                // A field holding `nil` has nothing to forward
+                if pointer && current_value != 0 {
+                    // we need to move *into* new_value_ptr the forwarded ptr for current_value
+                    children
+                        .entry(current_value)
+                        .or_default()
+                        .push(new_value_ptr);
                 }
             }
         }
@@ -226,6 +364,32 @@ impl Allocator {
         }
     }
 
+    fn is_string(&self, ptr: *const u8) -> bool {
+        self.strings.lock().unwrap().contains(&ptr)
+    }
+
+    /// Copies the string at `ptr` to to-space, returning the copy.
+    fn copy_string(
+        ptr: *const u8,
+        scratch: &Bump,
+        allocations: &mut Vec<*const u8>,
+        strings: &mut HashSet<*const u8>,
+    ) -> *const u8 {
+        let len = unsafe { CStr::from_ptr(ptr.cast()) }.to_bytes().len();
+        log(&format!(
+            "runtime: gc: forwarding string ({ptr:?}) (len: {len})"
+        ));
+        let new_str = scratch
+            .alloc_layout(Layout::array::<u64>(str_words(len)).unwrap())
+            .as_ptr();
+        unsafe {
+            std::ptr::copy(ptr, new_str, len + 1);
+        }
+        allocations.push(new_str);
+        strings.insert(new_str);
+        new_str
+    }
+
     fn forward_child_fields(
         reachable: &[(*const u8, *const u8)],
         children: &HashMap<u64, Vec<*mut u8>>,
@@ -247,6 +411,11 @@ impl Allocator {
     }
 }
 
+/// The number of words needed to hold a string of `len` bytes and its nul terminator.
+fn str_words(len: usize) -> usize {
+    len / 8 + 1
+}
+
 unsafe fn read_string(ptr: *const u8) -> (String, *const u8) {
     let label = CStr::from_ptr(ptr.cast());
     let count = label.to_bytes().len() + 1;
@@ -260,7 +429,7 @@ pub struct FrameInfo {
 }
 
 impl FrameInfo {
-    fn new(ptr: *const u8, size: i64) -> Self {
+    pub(crate) fn new(ptr: *const u8, size: i64) -> Self {
         Self { ptr, size }
     }
 }
@@ -268,7 +437,7 @@ impl FrameInfo {
 pub struct ClassMetadata {
     /// The descriptor string for the class.
     pub descriptor: String,
-    /// The number of fields in the class.
+    /// The number of words in the class, including its metadata.
     pub count: usize,
     /// The current(from-space) class pointer.
     pub class: *const u8,
@@ -298,14 +467,14 @@ impl ClassMetadata {
 /// if the string is not valid UTF-8.
 pub extern "C" fn alloc(descriptor: *const u8, fp: *const u8, size: i64) -> *const u64 {
     let frame = FrameInfo::new(fp, size);
-    let count = unsafe { CStr::from_ptr(descriptor.cast()) }
-        .to_bytes()
-        .len()
-        + CLASS_METADATA_FIELDS;
+    let fields = unsafe { CStr::from_ptr(descriptor.cast()) }
+        .to_str()
+        .unwrap();
+    let count = class_words(fields);
     match GLOBAL
         .lock()
         .unwrap()
-        .alloc(descriptor, count - CLASS_METADATA_FIELDS, frame, count, 0)
+        .alloc(descriptor, fields.len(), frame, count, 0)
     {
         Ok(ptr) => ptr.cast(),
         Err(msg) => panic!("{msg}"),
@@ -0,0 +1,33 @@
+use std::ffi::CStr;
+
+/// The index of the metadata field that holds the ID of an object's class.
+pub const CLASS_ID_FIELD: usize = 2;
+
+#[no_mangle]
+/// Whether the class of `object` is one of the classes with an ID in `[first, last]`. Each class
+/// and its subclasses are given consecutive IDs, so this tests whether the object is an instance
+/// of the class with ID `first`.
+pub extern "C" fn instance_of(object: *const u64, first: i64, last: i64) -> bool {
+    let id = unsafe { std::ptr::read(object.add(CLASS_ID_FIELD)) };
+    i64::try_from(id).is_ok_and(|id| (first..=last).contains(&id))
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if `object` is not an instance of the class `name`, or if the name is
+/// not valid UTF-8.
+pub extern "C" fn checked_cast(
+    object: *const u64,
+    first: i64,
+    last: i64,
+    name: *const u8,
+) -> *const u64 {
+    if instance_of(object, first, last) {
+        return object;
+    }
+    let name = unsafe { CStr::from_ptr(name.cast::<i8>()) };
+    panic!(
+        "runtime: cannot cast object to `{}`",
+        name.to_str().unwrap()
+    );
+}
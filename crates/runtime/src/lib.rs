@@ -1,7 +1,10 @@
 mod alloc;
+mod cast;
 mod cmp;
 mod print;
+mod string;
 
-pub use alloc::CLASS_METADATA_FIELDS;
+pub use alloc::{field_offsets, CLASS_METADATA_FIELDS, LAYOUT_FIELD};
+pub use cast::CLASS_ID_FIELD;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
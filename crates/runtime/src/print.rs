@@ -10,6 +10,14 @@ pub extern "C" fn println_int(i: i64) {
     println!("{i}");
 }
 
+#[no_mangle]
+pub extern "C" fn println_char(c: u32) {
+    println!(
+        "{}",
+        char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)
+    );
+}
+
 #[no_mangle]
 pub extern "C" fn println_float(f: f64) {
     println!("{f}");
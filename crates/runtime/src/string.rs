@@ -0,0 +1,177 @@
+use crate::alloc::{FrameInfo, GLOBAL};
+use std::{cmp::Ordering, ffi::CStr};
+
+/// Reads the bytes of the nul-terminated string at `s`.
+///
+/// The bytes are copied, since the string may be moved by a garbage collection before it's used.
+fn bytes(s: *const u8) -> Vec<u8> {
+    unsafe { CStr::from_ptr(s.cast()) }.to_bytes().to_vec()
+}
+
+/// Allocates a new string holding `bytes`.
+fn allocate(bytes: &[u8], fp: *const u8, size: i64) -> *const u8 {
+    let frame = FrameInfo::new(fp, size);
+    match GLOBAL.lock().unwrap().alloc_str(bytes, frame, 0) {
+        Ok(ptr) => ptr,
+        Err(msg) => panic!("{msg}"),
+    }
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if the allocation fails.
+pub extern "C" fn str_concat(a: *const u8, b: *const u8, fp: *const u8, size: i64) -> *const u8 {
+    let mut joined = bytes(a);
+    joined.extend(bytes(b));
+    allocate(&joined, fp, size)
+}
+
+#[no_mangle]
+/// Compares the contents of two strings, returning a negative number, zero or a positive number
+/// when `a` is ordered before, the same as or after `b`.
+pub extern "C" fn str_compare(a: *const u8, b: *const u8) -> i64 {
+    match bytes(a).cmp(&bytes(b)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+#[no_mangle]
+/// The length of a string in bytes.
+///
+/// # Panics
+/// This function will panic if the length does not fit in an `i64`.
+pub extern "C" fn len(s: *const u8) -> i64 {
+    i64::try_from(unsafe { CStr::from_ptr(s.cast()) }.to_bytes().len()).unwrap()
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if `[start, end)` is not a range of bytes in `s`, or if the
+/// allocation fails.
+pub extern "C" fn substring(
+    s: *const u8,
+    start: i64,
+    end: i64,
+    fp: *const u8,
+    size: i64,
+) -> *const u8 {
+    let s = bytes(s);
+    let range = usize::try_from(start)
+        .ok()
+        .zip(usize::try_from(end).ok())
+        .filter(|&(start, end)| start <= end && end <= s.len());
+    let Some((start, end)) = range else {
+        panic!(
+            "runtime: substring: range [{start}, {end}) is out of bounds for a string of length {}",
+            s.len()
+        );
+    };
+    allocate(&s[start..end], fp, size)
+}
+
+/// Decodes the character starting at byte `index` of `s` in place, reading no more than the
+/// bytes of that character. Returns `None` at the end of `s`, along with its width in bytes.
+///
+/// A byte that doesn't start a valid character is read on its own as U+FFFD, so that walking `s`
+/// one character at a time visits every byte of it.
+fn decode(s: *const u8, index: i64) -> Option<(char, usize)> {
+    let start = unsafe { s.add(usize::try_from(index).ok()?) };
+    let first = unsafe { *start };
+    let width = match first {
+        0 => return None,
+        0x01..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 0,
+    };
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate().take(width) {
+        *byte = unsafe { *start.add(i) };
+        // A character cut short by the end of `s` is invalid, and nothing past it is read
+        if *byte == 0 {
+            break;
+        }
+    }
+    let c = std::str::from_utf8(&bytes[..width])
+        .ok()
+        .and_then(|c| c.chars().next());
+    Some(c.map_or((char::REPLACEMENT_CHARACTER, 1), |c| (c, width)))
+}
+
+#[no_mangle]
+/// The character starting at byte `index` of `s`.
+///
+/// # Panics
+/// This function will panic if `index` is out of bounds or not at the start of a character.
+pub extern "C" fn str_index(s: *const u8, index: i64) -> u32 {
+    let length = len(s);
+    // A continuation byte is in the middle of a character
+    let boundary = usize::try_from(index)
+        .ok()
+        .filter(|_| index < length)
+        .is_some_and(|i| !matches!(unsafe { *s.add(i) }, 0x80..=0xBF));
+    let Some((c, _)) = decode(s, index).filter(|_| boundary) else {
+        panic!(
+            "runtime: index {index} is out of bounds or not a character boundary for a string of length {length}"
+        );
+    };
+    c.into()
+}
+
+#[no_mangle]
+/// The character starting at byte `index` of `s`, which is only called with an `index` that a
+/// loop over `s` has already checked is within it.
+///
+/// # Panics
+/// This function will panic if `index` is at the end of `s`.
+pub extern "C" fn str_char(s: *const u8, index: i64) -> u32 {
+    let Some((c, _)) = decode(s, index) else {
+        panic!("runtime: index {index} is at the end of the string");
+    };
+    c.into()
+}
+
+#[no_mangle]
+/// The byte index of the character after the one starting at byte `index` of `s`, which is the
+/// length of `s` for its last character. Like `str_char`, it's only called by a loop over `s`.
+///
+/// # Panics
+/// This function will panic if `index` is at the end of `s`.
+pub extern "C" fn str_next(s: *const u8, index: i64) -> i64 {
+    let Some((_, width)) = decode(s, index) else {
+        panic!("runtime: index {index} is at the end of the string");
+    };
+    index + i64::try_from(width).unwrap()
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if the allocation fails.
+pub extern "C" fn int_to_str(i: i64, fp: *const u8, size: i64) -> *const u8 {
+    allocate(i.to_string().as_bytes(), fp, size)
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if the allocation fails.
+pub extern "C" fn float_to_str(f: f64, fp: *const u8, size: i64) -> *const u8 {
+    allocate(f.to_string().as_bytes(), fp, size)
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if the allocation fails.
+pub extern "C" fn char_to_str(c: u32, fp: *const u8, size: i64) -> *const u8 {
+    let c = char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER);
+    allocate(c.to_string().as_bytes(), fp, size)
+}
+
+#[no_mangle]
+/// # Panics
+/// This function will panic if the allocation fails.
+pub extern "C" fn bool_to_str(b: bool, fp: *const u8, size: i64) -> *const u8 {
+    allocate(b.to_string().as_bytes(), fp, size)
+}